@@ -1,3 +1,6 @@
+// the formatting variants are benchmarked as written, format! and all
+#![allow(clippy::useless_format)]
+
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 // -- BYTE SIZE FORMATTING -- //
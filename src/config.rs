@@ -0,0 +1,145 @@
+// mini config file, read once at startup from $FMIN_HOME/.fminrc (or wherever the data dir is)
+//
+// file contents look like:
+// # ask before these file operations? yes/no
+// confirm copy = yes
+// confirm move = yes
+// confirm trash = no
+//
+// # how to open files on Enter. first matching pattern wins
+// open *.pdf = zathura
+// open *.md in terminal = vim
+//
+// # extra programs to offer in the open-with list, for any file
+// open with gimp = gimp
+// open with hexdump in terminal = xxd {} | less
+//
+// # how much memory to spend remembering dirs, so going back to one is instant. 0 turns it off
+// dir cache size = 100 MB
+//
+// where anything missing keeps its default
+
+use std::path::Path;
+
+pub struct Config {
+    pub confirm_copy: bool,
+    pub confirm_move: bool,
+    pub confirm_trash: bool,
+    pub confirm_undo: bool,
+    pub open_rules: Vec<OpenRule>,
+    pub open_with: Vec<Opener>,
+    // in bytes
+    pub dir_cache_size: usize,
+}
+
+// eg. `open *.md in terminal = vim`
+pub struct OpenRule {
+    // matched against the filename, like *.pdf or Makefile
+    pub pattern: String,
+    pub opener: Opener,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Opener {
+    // what the open-with list shows, like "Firefox"
+    pub name: String,
+    // a shell command, which gets the file path as "$1"
+    pub command: String,
+    // terminal programs get the whole terminal until they exit,
+    // while everything else runs in the background
+    pub in_terminal: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        // better to ask once too often than to lose files to a stray keypress
+        Config {
+            confirm_copy: true,
+            confirm_move: true,
+            confirm_trash: true,
+            confirm_undo: true,
+            open_rules: Vec::new(),
+            open_with: Vec::new(),
+            // enough for a few dirs with 100k+ entries
+            dir_cache_size: 100 * 1000 * 1000,
+        }
+    }
+}
+
+// a missing or unreadable file just means the defaults
+pub fn read_config(filepath: &Path) -> Config {
+    let mut config = Config::default();
+    let contents = match std::fs::read_to_string(filepath) {
+        Ok(contents) => contents,
+        Err(_) => return config,
+    };
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+        let Some((key, value)) = line.split_once('=') else {
+            log::info!("skipping bad config line: {}", line);
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        if let Some(name) = key.strip_prefix("open with ") {
+            let (name, in_terminal) = strip_in_terminal(name);
+            config.open_with.push(Opener { name: name.to_string(), command: value.to_string(), in_terminal });
+            continue;
+        }
+        if let Some(pattern) = key.strip_prefix("open ") {
+            let (pattern, in_terminal) = strip_in_terminal(pattern);
+            let opener = Opener { name: value.to_string(), command: value.to_string(), in_terminal };
+            config.open_rules.push(OpenRule { pattern: pattern.to_string(), opener });
+            continue;
+        }
+        if key == "dir cache size" {
+            match parse_megabytes(value) {
+                Some(num_bytes) => config.dir_cache_size = num_bytes,
+                None => log::info!("skipping config line, expected a size like 100 MB: {}", line),
+            };
+            continue;
+        }
+        // permanent deletes always ask, there's no undoing them
+        if key == "confirm delete" {
+            log::info!("skipping config line, permanent deletes always ask first: {}", line);
+            continue;
+        }
+        let setting = match key {
+            "confirm copy" => &mut config.confirm_copy,
+            "confirm move" => &mut config.confirm_move,
+            "confirm trash" => &mut config.confirm_trash,
+            "confirm undo" => &mut config.confirm_undo,
+            _ => {
+                log::info!("skipping unknown config key: {}", key);
+                continue;
+            },
+        };
+        match parse_bool(value) {
+            Some(value) => *setting = value,
+            None => log::info!("skipping config line, expected yes or no: {}", line),
+        };
+    }
+    config
+}
+
+// eg. "*.md in terminal" -> ("*.md", true)
+fn strip_in_terminal(key: &str) -> (&str, bool) {
+    match key.strip_suffix(" in terminal") {
+        Some(key) => (key.trim(), true),
+        None => (key.trim(), false),
+    }
+}
+
+// eg. "100 MB" or just "100" -> 100_000_000 bytes
+fn parse_megabytes(value: &str) -> Option<usize> {
+    let value = value.strip_suffix("MB").unwrap_or(value).trim();
+    value.parse::<usize>().ok()?.checked_mul(1000 * 1000)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "yes" | "true" | "on" => Some(true),
+        "no" | "false" | "off" => Some(false),
+        _ => None,
+    }
+}
@@ -0,0 +1,98 @@
+// mini dir cache, so going back and forth between dirs doesn't reread and resort them every time.
+// a cached dir is only reused while its mtime is unchanged, which covers adding, removing and renaming entries.
+// edits inside files don't touch the dir's mtime, so their sizes and dates can be out of date
+// until the dir is refreshed, eg. by the watcher
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::{Entry, SortBy};
+
+// mtimes can be as coarse as 2s (eg. FAT), so a dir changed this recently could change again
+// without its mtime moving, and the cached entries would look up to date when they're not
+const RACY_MTIME_WINDOW : Duration = Duration::from_secs(2);
+
+pub struct DirCache {
+    dirs: HashMap<PathBuf, CachedDir>,
+    // roughly how much memory the cached entries take, kept under max_bytes
+    num_bytes: usize,
+    max_bytes: usize,
+    // counts up on every use, for finding the least recently used dir
+    clock: u64,
+}
+
+struct CachedDir {
+    modified: SystemTime,
+    sort: SortBy,
+    entries: Vec<Entry>,
+    num_bytes: usize,
+    last_used: u64,
+}
+
+impl DirCache {
+    // a max of 0 turns the cache off
+    pub fn new(max_bytes: usize) -> DirCache {
+        DirCache { dirs: HashMap::new(), num_bytes: 0, max_bytes, clock: 0 }
+    }
+
+    // the entries as they were cached, and how they were sorted then.
+    // None if the dir has changed since, or was never cached
+    pub fn get(&mut self, dir: &Path, modified: SystemTime) -> Option<(Vec<Entry>, SortBy)> {
+        let cached = self.dirs.get_mut(dir)?;
+        if cached.modified != modified {
+            self.remove(dir);
+            return None;
+        }
+        self.clock += 1;
+        cached.last_used = self.clock;
+        Some((cached.entries.clone(), cached.sort))
+    }
+
+    // only for fully loaded entries, so nothing's ever stuck on "loading…".
+    // modified should be read before the entries were
+    pub fn insert(&mut self, dir: &Path, modified: SystemTime, sort: SortBy, entries: &[Entry]) {
+        self.remove(dir);
+        // a clock that's behind the disk's counts as recent too
+        let recently_modified = SystemTime::now()
+            .duration_since(modified)
+            .map_or(true, |age| age < RACY_MTIME_WINDOW);
+        if recently_modified { return; }
+        let num_bytes = estimate_num_bytes(entries);
+        // one huge dir would push out everything else and still not fit
+        if num_bytes > self.max_bytes { return; }
+        while self.num_bytes + num_bytes > self.max_bytes {
+            let least_recently_used = self.dirs
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(dir, _)| dir.clone());
+            match least_recently_used {
+                Some(dir) => self.remove(&dir),
+                None => break,
+            };
+        }
+        self.clock += 1;
+        self.num_bytes += num_bytes;
+        self.dirs.insert(dir.to_path_buf(), CachedDir {
+            modified,
+            sort,
+            entries: entries.to_vec(),
+            num_bytes,
+            last_used: self.clock,
+        });
+    }
+
+    fn remove(&mut self, dir: &Path) {
+        if let Some(cached) = self.dirs.remove(dir) {
+            self.num_bytes -= cached.num_bytes;
+        }
+    }
+}
+
+// the entries themselves, plus the strings they point to
+fn estimate_num_bytes(entries: &[Entry]) -> usize {
+    entries
+        .iter()
+        .map(|entry| std::mem::size_of::<Entry>() + entry.path.as_os_str().len() + entry.name.0.len())
+        .sum()
+}
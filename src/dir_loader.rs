@@ -0,0 +1,162 @@
+// mini dir loader, for huge dirs on slow disks: names come from one quick readdir and show up right away,
+// while the size and date of each entry are read on another thread and arrive a batch at a time
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+
+use crate::{Entry, FileDate, FileName, FileSize, Message};
+
+// often enough for the rows to visibly fill in, without a redraw per entry
+const BATCH_INTERVAL : Duration = Duration::from_millis(50);
+
+pub struct LoadedMetadata {
+    pub path: PathBuf,
+    pub size: Option<FileSize>,
+    pub date: Option<FileDate>,
+}
+
+// the thread reading metadata, which sends it back as Message::MetadataLoaded.
+// dropping this stops the thread, eg. after leaving the dir before it's done
+pub struct MetadataLoader {
+    // tells its messages apart from ones an older loader sent just before it was dropped
+    pub id: usize,
+    stopped: Arc<AtomicBool>,
+}
+
+impl MetadataLoader {
+    // reads metadata in the order given, so the entries on screen fill in first
+    pub fn start(entries: &[Entry], messages: Sender<Message>) -> MetadataLoader {
+        static NEXT_ID : AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let paths = entries
+            .iter()
+            .map(|entry| (entry.path.clone(), entry.is_dir))
+            .collect::<Vec<(PathBuf, bool)>>();
+        std::thread::spawn(move || {
+            let mut batch = Vec::new();
+            let mut last_sent = Instant::now();
+            for (path, is_dir) in paths {
+                if thread_stopped.load(Ordering::Relaxed) { return; }
+                let (size, date) = read_metadata(&path, is_dir);
+                batch.push(LoadedMetadata { path, size, date });
+                if last_sent.elapsed() >= BATCH_INTERVAL {
+                    let message = Message::MetadataLoaded { loader_id: id, loaded: std::mem::take(&mut batch), finished: false };
+                    if messages.send(message).is_err() { return; }
+                    last_sent = Instant::now();
+                }
+            }
+            let _ = messages.send(Message::MetadataLoaded { loader_id: id, loaded: batch, finished: true });
+        });
+        MetadataLoader { id, stopped }
+    }
+}
+
+impl Drop for MetadataLoader {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+// fills in entries from the start until time runs out, without another thread.
+// returns how many it got to
+pub fn read_metadata_until(entries: &mut [Entry], deadline: Instant) -> usize {
+    for (index, entry) in entries.iter_mut().enumerate() {
+        if Instant::now() >= deadline { return index; }
+        let (size, date) = read_metadata(&entry.path, entry.is_dir);
+        entry.size = size;
+        entry.date = date;
+        entry.loading = false;
+    }
+    entries.len()
+}
+
+// just the names, with size and date still loading.
+// readdir usually knows what's a dir already, so only symlinks need an extra stat
+pub fn read_entry_names(dir: &Path) -> Vec<Entry> {
+    let Ok(dir_entries) = dir.read_dir() else { return Vec::new(); };
+    dir_entries
+        .filter_map(|dir_entry| dir_entry.ok())
+        .map(|dir_entry| {
+            let path = dir_entry.path();
+            let is_dir = match dir_entry.file_type() {
+                Ok(file_type) if file_type.is_symlink() => path.is_dir(),
+                Ok(file_type) => file_type.is_dir(),
+                Err(_) => path.is_dir(),
+            };
+            loading_entry(path, is_dir)
+        })
+        .collect()
+}
+
+// one entry, with its size and date, eg. after the watcher saw it change. None if it's gone
+pub fn read_entry(path: &Path) -> Option<Entry> {
+    let file_type = std::fs::symlink_metadata(path).ok()?.file_type();
+    let is_dir = match file_type.is_symlink() {
+        true => path.is_dir(),
+        false => file_type.is_dir(),
+    };
+    let (size, date) = read_metadata(path, is_dir);
+    Some(Entry { size, date, loading: false, ..loading_entry(path.to_path_buf(), is_dir) })
+}
+
+fn loading_entry(path: PathBuf, is_dir: bool) -> Entry {
+    // names needn't be utf-8, and a path like / or .. has none at all
+    let mut name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string());
+    if is_dir {
+        name = format!("{}/", name);
+    }
+    Entry {
+        path,
+        is_dir,
+        name: FileName(name),
+        size: None,
+        date: None,
+        loading: true,
+    }
+}
+
+// like ls -l, a symlink's own size and date rather than its target's
+pub fn read_metadata(path: &Path, is_dir: bool) -> (Option<FileSize>, Option<FileDate>) {
+    let Ok(metadata) = std::fs::symlink_metadata(path) else { return (None, None); };
+    // dir.metadata.len just returns size of os-dir file object thingy, which is not useful
+    // its not actually related to size of contents
+    let size = match is_dir {
+        true => None,
+        false => Some(FileSize(metadata.len())),
+    };
+    // apparently some platforms do not have mtime / ftLastWriteTime available
+    // https://doc.rust-lang.org/std/fs/struct.Metadata.html#errors
+    let date = metadata.modified().ok().map(|system_time| FileDate(DateTime::<Local>::from(system_time)));
+    (size, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn names_that_arent_utf8_still_load() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = std::env::temp_dir().join(format!("fmin_test_{}_{}", "non_utf8_names", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(OsStr::from_bytes(b"sub\xfe"))).unwrap();
+        std::fs::write(dir.join(OsStr::from_bytes(b"file\xff")), "").unwrap();
+
+        let mut names: Vec<String> = read_entry_names(&dir).into_iter().map(|entry| entry.name.to_string()).collect();
+        names.sort();
+        assert_eq!(names, vec!["file\u{fffd}", "sub\u{fffd}/"]);
+        let entry = read_entry(&dir.join(OsStr::from_bytes(b"file\xff"))).unwrap();
+        assert_eq!(entry.name.to_string(), "file\u{fffd}");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
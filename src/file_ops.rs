@@ -0,0 +1,332 @@
+// mini helpers for copying, moving, renaming, and removing files and whole dirs
+
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+// shared between a long operation on another thread and whoever's watching it.
+// atomics instead of messages, since the watcher only needs the latest numbers
+#[derive(Default)]
+pub struct Progress {
+    // 0 until counted, which happens before anything else
+    pub total_files: AtomicUsize,
+    pub total_bytes: AtomicU64,
+    pub files_done: AtomicUsize,
+    pub bytes_done: AtomicU64,
+    // checked between chunks, so a huge file stops within a moment too
+    pub cancelled: AtomicBool,
+}
+
+impl Progress {
+    fn check_cancelled(&self) -> io::Result<()> {
+        match self.cancelled.load(Ordering::Relaxed) {
+            true => Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled")),
+            false => Ok(()),
+        }
+    }
+}
+
+// like `cp -r`, but never follows symlinks, so a link to a parent dir can't copy forever.
+// a half-finished copy is removed again, but only if this created `to`,
+// so something that got there first (eg. from another program) is left alone
+pub fn copy_recursively(from: &Path, to: &Path, progress: &Progress) -> io::Result<()> {
+    let mut created = false;
+    let result = copy_into_new(from, to, progress, &mut created);
+    if result.is_err() && created {
+        let _ = remove_recursively(to, &Progress::default());
+    }
+    result
+}
+
+// sets created once `to` exists and is ours, which is before anything can fail halfway
+fn copy_into_new(from: &Path, to: &Path, progress: &Progress, created: &mut bool) -> io::Result<()> {
+    if to.starts_with(from) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "can't copy a dir into itself"));
+    }
+    progress.check_cancelled()?;
+    let metadata = std::fs::symlink_metadata(from)?;
+    if metadata.file_type().is_symlink() {
+        copy_symlink(from, to)?;
+        *created = true;
+        progress.files_done.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    } else if metadata.is_dir() {
+        std::fs::create_dir(to)?;
+        *created = true;
+        for dir_entry in std::fs::read_dir(from)? {
+            let dir_entry = dir_entry?;
+            // everything inside is ours already
+            copy_into_new(&dir_entry.path(), &to.join(dir_entry.file_name()), progress, &mut true)?;
+        }
+        // set permissions last, in case they don't let us write into the new dir
+        std::fs::set_permissions(to, metadata.permissions())
+    } else {
+        copy_file(from, to, progress, created)?;
+        std::fs::set_permissions(to, metadata.permissions())?;
+        progress.files_done.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+// like std::fs::copy, but a chunk at a time, to report progress and stop when cancelled
+fn copy_file(from: &Path, to: &Path, progress: &Progress, created: &mut bool) -> io::Result<()> {
+    const CHUNK_SIZE : usize = 1024 * 1024;
+    let mut reader = File::open(from)?;
+    // never overwrite something that's already there
+    let mut writer = std::fs::OpenOptions::new().write(true).create_new(true).open(to)?;
+    *created = true;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        progress.check_cancelled()?;
+        let num_bytes = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(num_bytes) => num_bytes,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        writer.write_all(&buffer[..num_bytes])?;
+        progress.bytes_done.fetch_add(num_bytes as u64, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    // windows symlinks need to know if they point to a file or dir, and extra privileges.
+    // copying what they point to is close enough
+    std::fs::copy(from, to).map(|_num_bytes| ())
+}
+
+// like `mv`: a cheap rename when possible, else copy everything and remove the original
+pub fn move_path(from: &Path, to: &Path, progress: &Progress) -> io::Result<()> {
+    if to.starts_with(from) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "can't move a dir into itself"));
+    }
+    match std::fs::rename(from, to) {
+        Err(err) if is_cross_device(&err) => {
+            // leaves no half-copied leftovers; the original is still intact
+            copy_recursively(from, to, progress)?;
+            // too late to cancel once everything's copied, or the original would be half gone
+            remove_recursively(from, &Progress::default())
+        },
+        result => result,
+    }
+}
+
+fn is_cross_device(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    return err.raw_os_error() == Some(libc::EXDEV);
+    #[cfg(not(unix))]
+    return err.raw_os_error() == Some(17); // ERROR_NOT_SAME_DEVICE
+}
+
+// like `rm -r`, removing a symlink itself rather than what it points to
+pub fn remove_recursively(path: &Path, progress: &Progress) -> io::Result<()> {
+    progress.check_cancelled()?;
+    let metadata = std::fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        for dir_entry in std::fs::read_dir(path)? {
+            remove_recursively(&dir_entry?.path(), progress)?;
+        }
+        std::fs::remove_dir(path)
+    } else {
+        std::fs::remove_file(path)?;
+        progress.files_done.fetch_add(1, Ordering::Relaxed);
+        progress.bytes_done.fetch_add(metadata.len(), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+// counts the files and bytes inside paths, like `du -s`, without following symlinks.
+// unreadable entries are skipped, since this is only for showing a summary
+pub fn measure(paths: &[PathBuf]) -> (usize, u64) {
+    let mut num_files = 0;
+    let mut num_bytes = 0;
+    let mut unvisited = paths.to_vec();
+    while let Some(path) = unvisited.pop() {
+        let Ok(metadata) = std::fs::symlink_metadata(&path) else { continue; };
+        if metadata.is_dir() {
+            if let Ok(dir_entries) = std::fs::read_dir(&path) {
+                unvisited.extend(dir_entries.filter_map(|dir_entry| dir_entry.ok()).map(|dir_entry| dir_entry.path()));
+            }
+        } else {
+            num_files += 1;
+            num_bytes += metadata.len();
+        }
+    }
+    (num_files, num_bytes)
+}
+
+// picks a name that isn't taken yet, eg. notes.txt -> notes_1.txt -> notes_2.txt
+pub fn unique_destination(path: &Path) -> PathBuf {
+    let mut n = 0;
+    loop {
+        let candidate = numbered_filename(path, n);
+        if std::fs::symlink_metadata(&candidate).is_err() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+// eg. notes.txt, 2 -> notes_2.txt. 0 leaves the name as is
+pub fn numbered_filename(path: &Path, n: usize) -> PathBuf {
+    if n == 0 {
+        return path.to_path_buf();
+    }
+    // built from OsStrs, so names that aren't valid unicode survive
+    let mut filename = path.file_stem().unwrap_or_default().to_os_string();
+    filename.push(format!("_{}", n));
+    if let Some(extension) = path.extension() {
+        filename.push(".");
+        filename.push(extension);
+    }
+    path.with_file_name(filename)
+}
+
+// --- batch rename --- //
+
+// matches the edited names, one per line, to the original paths in order, and checks they make sense
+// as a whole before anything is renamed. returns only the pairs that actually change
+pub fn plan_renames(originals: &[PathBuf], edited_text: &str) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let edited_names = edited_text.lines().collect::<Vec<&str>>();
+    if edited_names.len() != originals.len() {
+        return Err(format!("expected {} lines but got {}, so nothing was renamed", originals.len(), edited_names.len()));
+    }
+
+    let mut renames = Vec::new();
+    let mut targets = HashSet::new();
+    for (original, edited_name) in originals.iter().zip(edited_names) {
+        let original_name = original.file_name().unwrap_or_default();
+        // untouched lines stay as is, even names that aren't valid unicode and got mangled in the editor
+        let target = match edited_name == original_name.to_string_lossy() {
+            true => original.clone(),
+            false => original.with_file_name(edited_name),
+        };
+        if edited_name.trim().is_empty() {
+            return Err("empty names aren't allowed, so nothing was renamed".to_string());
+        }
+        if edited_name.contains('/') || edited_name == "." || edited_name == ".." {
+            return Err(format!("{} isn't a plain name, so nothing was renamed", edited_name));
+        }
+        if !targets.insert(target.clone()) {
+            return Err(format!("{} appears twice, so nothing was renamed", edited_name));
+        }
+        if &target != original {
+            renames.push((original.clone(), target));
+        }
+    }
+
+    // renaming onto a file that isn't part of the batch would overwrite it
+    let sources = originals.iter().collect::<HashSet<&PathBuf>>();
+    for (_, target) in &renames {
+        if !sources.contains(target) && std::fs::symlink_metadata(target).is_ok() {
+            return Err(format!("{} already exists, so nothing was renamed", target.display()));
+        }
+    }
+    Ok(renames)
+}
+
+// renames everything out of the way first, then into place,
+// so swaps (a <-> b) and cycles (a -> b -> c -> a) don't clobber each other.
+// returns the renames that went through, and errors for the ones that didn't
+pub fn apply_renames(renames: &[(PathBuf, PathBuf)]) -> (Vec<(PathBuf, PathBuf)>, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut moved_aside = Vec::new();
+    for (i, (from, to)) in renames.iter().enumerate() {
+        let mut temp_name = OsString::from(format!(".fmin_rename_{}_{}_", std::process::id(), i));
+        temp_name.push(from.file_name().unwrap_or_default());
+        let temp_path = from.with_file_name(temp_name);
+        match std::fs::rename(from, &temp_path) {
+            Ok(()) => moved_aside.push((from, temp_path, to)),
+            Err(err) => errors.push(format!("{}: {}", from.display(), err)),
+        }
+    }
+
+    let mut done = Vec::new();
+    for (from, temp_path, to) in moved_aside {
+        match std::fs::rename(&temp_path, to) {
+            Ok(()) => done.push((from.clone(), to.clone())),
+            Err(err) => {
+                // put it back how it was, unless something else took its old name in a swap
+                let put_back = std::fs::symlink_metadata(from).is_err() && std::fs::rename(&temp_path, from).is_ok();
+                match put_back {
+                    true => errors.push(format!("{}: {}", from.display(), err)),
+                    false => errors.push(format!("{}: {}, left at {}", from.display(), err, temp_path.display())),
+                }
+            },
+        }
+    }
+    (done, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fresh dir with these files in it, and their paths in the same order
+    fn dir_with_files(test_name: &str, names: &[&str]) -> (PathBuf, Vec<PathBuf>) {
+        let dir = std::env::temp_dir().join(format!("fmin_test_{}_{}", test_name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = names.iter().map(|name| dir.join(name)).collect::<Vec<PathBuf>>();
+        for path in &paths {
+            File::create(path).unwrap();
+        }
+        (dir, paths)
+    }
+
+    #[test]
+    fn plan_renames_skips_unchanged_names() {
+        let (dir, paths) = dir_with_files("plan_unchanged", &["a", "b"]);
+        assert_eq!(plan_renames(&paths, "a\nb\n"), Ok(vec![]));
+        assert_eq!(plan_renames(&paths, "a\nc\n"), Ok(vec![(dir.join("b"), dir.join("c"))]));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn plan_renames_allows_swaps_and_cycles() {
+        let (dir, paths) = dir_with_files("plan_swaps", &["a", "b", "c"]);
+        assert_eq!(plan_renames(&paths, "b\na\nc\n"), Ok(vec![
+            (dir.join("a"), dir.join("b")),
+            (dir.join("b"), dir.join("a")),
+        ]));
+        assert_eq!(plan_renames(&paths, "b\nc\na\n").map(|renames| renames.len()), Ok(3));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn plan_renames_rejects_bad_edits() {
+        let (dir, paths) = dir_with_files("plan_bad", &["a", "b"]);
+        File::create(dir.join("taken")).unwrap();
+        // wrong number of lines, eg. one deleted by accident
+        assert!(plan_renames(&paths, "a\n").is_err());
+        assert!(plan_renames(&paths, "a\nb\nc\n").is_err());
+        // two entries can't end up with the same name
+        assert!(plan_renames(&paths, "c\nc\n").is_err());
+        assert!(plan_renames(&paths, "b\nb\n").is_err());
+        assert!(plan_renames(&paths, "a\n \n").is_err());
+        assert!(plan_renames(&paths, "a\nsub/b\n").is_err());
+        assert!(plan_renames(&paths, "a\n..\n").is_err());
+        // would overwrite something outside the batch
+        assert!(plan_renames(&paths, "a\ntaken\n").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+    #[test]
+    fn failed_copy_leaves_existing_destination_alone() {
+        let (dir, paths) = dir_with_files("copy_existing", &["a", "b"]);
+        std::fs::write(&paths[1], "not ours").unwrap();
+        // eg. another program created b between picking the name and copying
+        assert!(copy_recursively(&paths[0], &paths[1], &Progress::default()).is_err());
+        assert_eq!(std::fs::read_to_string(&paths[1]).unwrap(), "not ours");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,298 @@
+// mini undo journal: a log of file operations, with enough info to reverse them
+//
+// file contents look like:
+// #fmin_journal 1
+// batch,1700000000,trashed 2 entries
+// trash,/my/notes.txt,/home/me/.local/share/Trash/files/notes.txt
+// trash,/my/old%20dir,/home/me/.local/share/Trash/files/old%20dir
+// batch,1700000050,restored 1 entry
+// restore,/my/notes.txt,/home/me/.local/share/Trash/files/notes.txt
+// batch,1700000100,renamed 1 entry
+// rename,/my/a.txt,/my/b.txt
+//
+// where each batch is one user action, like a paste, and undo reverses the last batch.
+// paths are percent-encoded like in .trashinfo files, so they can't contain commas or newlines
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, TimeZone};
+
+use crate::file_ops::{self, Progress};
+use crate::{trash, append_to_filename, lock_file, write_file_atomically};
+
+const JOURNAL_HEADER : &str = "#fmin_journal";
+const JOURNAL_VERSION : usize = 1;
+// older batches are forgotten, so the file doesn't grow forever
+const MAX_BATCHES : usize = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JournalOp {
+    // a new copy or a new file. undo moves it to the trash, in case it's been edited since
+    Created(PathBuf),
+    Moved { from: PathBuf, to: PathBuf },
+    Trashed { original: PathBuf, trashed: PathBuf },
+    // put back from the trash. undo trashes it again, under whatever name is free then
+    Restored { original: PathBuf, trashed: PathBuf },
+    // like Moved, but part of a batch rename, so it's undone together with the rest of the batch
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+#[derive(Clone, Debug)]
+pub struct JournalBatch {
+    pub date: DateTime<Local>,
+    // eg. "copied 3 entries", for showing what undo just undid
+    pub description: String,
+    pub ops: Vec<JournalOp>,
+}
+
+pub fn record_batch(journal_path: &Path, batch: JournalBatch) -> io::Result<()> {
+    if batch.ops.is_empty() { return Ok(()); }
+    if let Some(dir) = journal_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // other fmin sessions share the journal
+    let _lock = lock_file(&append_to_filename(journal_path, ".lock"))?;
+    let mut batches = read_journal_file(journal_path)?;
+    batches.push(batch);
+    let num_forgotten = batches.len().saturating_sub(MAX_BATCHES);
+    write_journal_file(&batches[num_forgotten..], journal_path)
+}
+
+// removes the most recent batch from the journal and returns it, so it can be undone.
+// whatever can't be undone goes back in with record_batch(), to retry later
+pub fn take_last_batch(journal_path: &Path) -> io::Result<Option<JournalBatch>> {
+    if !journal_path.exists() { return Ok(None); }
+    let _lock = lock_file(&append_to_filename(journal_path, ".lock"))?;
+    let mut batches = read_journal_file(journal_path)?;
+    let last_batch = batches.pop();
+    write_journal_file(&batches, journal_path)?;
+    Ok(last_batch)
+}
+
+// the batch that undo would reverse next, without removing it
+pub fn last_batch(journal_path: &Path) -> io::Result<Option<JournalBatch>> {
+    Ok(read_journal_file(journal_path)?.pop())
+}
+
+// reverses each op, newest first. returns how many were undone, the conflicts that stopped the rest,
+// and the ops worth trying again, oldest first like in the batch.
+// ops whose entry isn't there anymore are dropped, since there's nothing left to undo
+pub fn undo_batch(batch: &JournalBatch) -> (usize, Vec<String>, Vec<JournalOp>) {
+    let mut num_undone = 0;
+    let mut conflicts = Vec::new();
+    let mut not_undone = Vec::new();
+
+    // renames are undone all at once, so swaps and cycles don't collide halfway through
+    let renames = batch.ops
+        .iter()
+        .filter_map(|op| match op {
+            JournalOp::Renamed { from, to } => Some((to.clone(), from.clone())),
+            _ => None,
+        })
+        .collect::<Vec<(PathBuf, PathBuf)>>();
+    if !renames.is_empty() {
+        let current_names = renames.iter().map(|(current, _)| current).collect::<Vec<&PathBuf>>();
+        let mut possible_renames = Vec::new();
+        let mut gone = Vec::new();
+        for (current, original) in &renames {
+            if std::fs::symlink_metadata(current).is_err() {
+                conflicts.push(format!("{} isn't there anymore", current.display()));
+                gone.push(current);
+            } else if !current_names.contains(&original) && std::fs::symlink_metadata(original).is_ok() {
+                conflicts.push(format!("{} already exists", original.display()));
+            } else {
+                possible_renames.push((current.clone(), original.clone()));
+            }
+        }
+        let (done, errors) = file_ops::apply_renames(&possible_renames);
+        num_undone += done.len();
+        conflicts.extend(errors);
+        for (current, original) in &renames {
+            if !gone.contains(&current) && !done.contains(&(current.clone(), original.clone())) {
+                not_undone.push(JournalOp::Renamed { from: original.clone(), to: current.clone() });
+            }
+        }
+    }
+
+    for op in batch.ops.iter().rev() {
+        let mut gone = false;
+        let result = match op {
+            JournalOp::Created(path) | JournalOp::Restored { original: path, .. } => match std::fs::symlink_metadata(path) {
+                Ok(_) => trash::move_to_trash(path, &Progress::default())
+                    .map(|_| ())
+                    .map_err(|err| format!("{}: {}", path.display(), err)),
+                Err(_) => {
+                    gone = true;
+                    Err(format!("{} isn't there anymore", path.display()))
+                },
+            },
+            JournalOp::Moved { from, to } => {
+                if std::fs::symlink_metadata(from).is_ok() {
+                    Err(format!("{} already exists", from.display()))
+                } else if std::fs::symlink_metadata(to).is_err() {
+                    gone = true;
+                    Err(format!("{} isn't there anymore", to.display()))
+                } else {
+                    file_ops::move_path(to, from, &Progress::default()).map_err(|err| format!("{}: {}", to.display(), err))
+                }
+            },
+            // eg. the trash was emptied since
+            JournalOp::Trashed { original, trashed } => match std::fs::symlink_metadata(trashed) {
+                Ok(_) => trash::restore_from_trash(trashed, &Progress::default())
+                    .map(|_| ())
+                    .map_err(|err| format!("{}: {}", original.display(), err)),
+                Err(_) => {
+                    gone = true;
+                    Err(format!("{} isn't in the trash anymore", original.display()))
+                },
+            },
+            JournalOp::Renamed { .. } => continue,
+        };
+        match result {
+            Ok(()) => num_undone += 1,
+            Err(err) => {
+                conflicts.push(err);
+                if !gone { not_undone.insert(0, op.clone()); }
+            },
+        }
+    }
+    (num_undone, conflicts, not_undone)
+}
+
+fn read_journal_file(journal_path: &Path) -> io::Result<Vec<JournalBatch>> {
+    let contents = match std::fs::read_to_string(journal_path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut lines = contents.lines().peekable();
+    if let Some(version) = lines.peek().and_then(|first_line| first_line.strip_prefix(JOURNAL_HEADER)) {
+        let version = version.trim().parse::<usize>().unwrap_or(0);
+        if version != JOURNAL_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown journal version {}", version)));
+        }
+        lines.next();
+    }
+
+    let mut batches: Vec<JournalBatch> = Vec::new();
+    for line in lines {
+        if line.is_empty() { continue; }
+        let chunks = line.splitn(3, ',').collect::<Vec<&str>>();
+        let op = match chunks[..] {
+            ["batch", date, description] => {
+                // a garbled date is out of range as often as not, and shouldn't lose the batch
+                let date = date.parse().ok().and_then(|secs| Local.timestamp_opt(secs, 0).single()).unwrap_or_else(Local::now);
+                batches.push(JournalBatch { date, description: description.to_string(), ops: Vec::new() });
+                continue;
+            },
+            ["create", path] => JournalOp::Created(trash::percent_decode(path)),
+            ["move", from, to] => JournalOp::Moved { from: trash::percent_decode(from), to: trash::percent_decode(to) },
+            ["trash", original, trashed] => JournalOp::Trashed { original: trash::percent_decode(original), trashed: trash::percent_decode(trashed) },
+            ["restore", original, trashed] => JournalOp::Restored { original: trash::percent_decode(original), trashed: trash::percent_decode(trashed) },
+            ["rename", from, to] => JournalOp::Renamed { from: trash::percent_decode(from), to: trash::percent_decode(to) },
+            _ => {
+                log::info!("skipping bad journal line: {}", line);
+                continue;
+            },
+        };
+        match batches.last_mut() {
+            Some(batch) => batch.ops.push(op),
+            None => log::info!("skipping journal line outside a batch: {}", line),
+        };
+    }
+    Ok(batches)
+}
+
+fn write_journal_file(batches: &[JournalBatch], journal_path: &Path) -> io::Result<()> {
+    let mut contents = format!("{} {}\n", JOURNAL_HEADER, JOURNAL_VERSION);
+    for batch in batches {
+        // the description is the last field, so commas are fine, but it has to stay on one line
+        contents.push_str(&format!("batch,{},{}\n", batch.date.timestamp(), batch.description.replace('\n', " ")));
+        for op in &batch.ops {
+            let line = match op {
+                JournalOp::Created(path) => format!("create,{}", trash::percent_encode(path)),
+                JournalOp::Moved { from, to } => format!("move,{},{}", trash::percent_encode(from), trash::percent_encode(to)),
+                JournalOp::Trashed { original, trashed } => format!("trash,{},{}", trash::percent_encode(original), trash::percent_encode(trashed)),
+                JournalOp::Restored { original, trashed } => format!("restore,{},{}", trash::percent_encode(original), trash::percent_encode(trashed)),
+                JournalOp::Renamed { from, to } => format!("rename,{},{}", trash::percent_encode(from), trash::percent_encode(to)),
+            };
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+    }
+    write_file_atomically(journal_path, contents.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fresh, empty dir for one test
+    fn test_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fmin_test_{}_{}", test_name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn batch(description: &str, ops: Vec<JournalOp>) -> JournalBatch {
+        JournalBatch { date: Local.timestamp_opt(1700000000, 0).unwrap(), description: description.to_string(), ops }
+    }
+
+    #[test]
+    fn journal_file_round_trips() {
+        let dir = test_dir("journal_round_trip");
+        let journal_path = dir.join("fmin.journal");
+        let ops = vec![
+            JournalOp::Created(dir.join("new file.txt")),
+            JournalOp::Moved { from: dir.join("a,b%c"), to: dir.join("sub/a,b%c") },
+            JournalOp::Trashed { original: dir.join("caf\u{e9}\nnotes"), trashed: dir.join("Trash/files/caf\u{e9}\nnotes") },
+            JournalOp::Renamed { from: dir.join("x"), to: dir.join("y") },
+            JournalOp::Restored { original: dir.join("old"), trashed: dir.join("Trash/files/old_1") },
+        ];
+        record_batch(&journal_path, batch("first", vec![JournalOp::Created(dir.join("one"))])).unwrap();
+        record_batch(&journal_path, batch("copied 3 entries, then\nsome", ops.clone())).unwrap();
+
+        let contents = std::fs::read_to_string(&journal_path).unwrap();
+        // one op per line, so encoded paths can't contain the separators
+        assert!(contents.contains("move,") && contents.contains("a%2Cb%25c"));
+        assert_eq!(contents.lines().count(), 1 + 2 + 1 + 5);
+
+        let last = last_batch(&journal_path).unwrap().unwrap();
+        assert_eq!(last.description, "copied 3 entries, then some");
+        assert_eq!(last.date.timestamp(), 1700000000);
+        assert_eq!(last.ops, ops);
+        assert_eq!(take_last_batch(&journal_path).unwrap().map(|batch| batch.ops), Some(ops));
+        assert_eq!(take_last_batch(&journal_path).unwrap().map(|batch| batch.description), Some("first".to_string()));
+        assert!(take_last_batch(&journal_path).unwrap().is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn undo_batch_keeps_only_what_can_be_retried() {
+        let dir = test_dir("journal_undo");
+        std::fs::create_dir(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/moved"), "").unwrap();
+        std::fs::write(dir.join("sub/blocked"), "").unwrap();
+        // something new took the original's place, so moving back would overwrite it
+        std::fs::write(dir.join("blocked"), "").unwrap();
+        let blocked = JournalOp::Moved { from: dir.join("blocked"), to: dir.join("sub/blocked") };
+        let ops = vec![
+            JournalOp::Moved { from: dir.join("moved"), to: dir.join("sub/moved") },
+            blocked.clone(),
+            // deleted since, or the trash was emptied. there's nothing left to undo
+            JournalOp::Moved { from: dir.join("gone"), to: dir.join("sub/gone") },
+            JournalOp::Created(dir.join("also gone")),
+            JournalOp::Trashed { original: dir.join("trashed"), trashed: dir.join("Trash/files/trashed") },
+            JournalOp::Restored { original: dir.join("restored"), trashed: dir.join("Trash/files/restored") },
+        ];
+
+        let (num_undone, conflicts, not_undone) = undo_batch(&batch("moved 6 entries", ops));
+        assert_eq!(num_undone, 1);
+        assert!(dir.join("moved").exists() && !dir.join("sub/moved").exists());
+        assert_eq!(conflicts.len(), 5);
+        assert_eq!(not_undone, vec![blocked]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
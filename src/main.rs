@@ -1,1221 +1,4050 @@
-// fmin, a terminal file manager inspired by fman + vim
-
-#![allow(unused_variables)]
-#![allow(unused_imports)]
-
-use std::cmp::Ordering;
-use std::collections::{HashSet, HashMap};
-use std::fmt::{Display, Formatter, Error};
-use std::fs::{DirEntry, File};
-use std::hash::{Hash, Hasher};
-use std::io::{BufRead, BufReader, Write};
-use std::path::{Path, PathBuf};
-
-use binary_heap_plus::BinaryHeap;
-use chrono::{DateTime, Datelike, TimeZone, Local};
-use crossterm::{
-    terminal,
-    queue,
-    execute,
-    cursor::{MoveTo, MoveToColumn, MoveToRow, MoveToNextLine, MoveToPreviousLine},
-    style::{Print, Color, SetBackgroundColor, SetForegroundColor, ResetColor},
-    event::{
-        read as await_next_event, 
-        Event, 
-        KeyCode, 
-        KeyEvent, 
-        KeyModifiers,
-    },
-};
-use log::{info};
-
-use crate::tui_program::{Program, UpdateResult};
-
-mod tui_program;
-
-// --- for debugging
-
-struct LoggerToFile<'a> {
-    path: &'a str,
-}
-
-impl log::Log for LoggerToFile<'_> {
-    fn flush(&self) {}
-    fn enabled(&self, metadata: &log::Metadata) -> bool { true }
-    fn log(&self, record: &log::Record) {
-        if self.enabled(record.metadata()) {
-            let mut file = std::fs::OpenOptions::new()
-                .append(true)
-                .create(true)
-                .open(&self.path)
-                // not sure what to do when log file breaks
-                // either silently fail or crash. i choose crash
-                .expect("failure to open or read log file");
-            writeln!(file, "{}", record.args());
-        }
-    }
-}
-
-static LOGGER : LoggerToFile = LoggerToFile { path: "/tmp/fmin_log" };
-
-// --- MODEL, and other data structures --- //
-
-struct Model {
-    mode: Mode,
-    cwd: PathBuf,
-    cwd_sort: SortBy,
-    // small optimization i think: sort entries at cd time, then filter from the sorted list on
-    // each keypress
-    // rather than sort from filtered list every keypress
-    // should calculate and store filtered and sorted entries at cd time
-    // so that less work is required during render time
-    // all_entries -> sort -> filter -> viewable slice of entries
-    // all_entries: HashSet<Entry>,
-    sorted_entries: Vec<Entry>,
-    filter_text: String,
-    cols: usize,
-    rows: usize,
-    list_view: ListViewData,
-    // model.history_filepath file contents should be like:
-    // n,1,my/path
-    // S,20,other/path
-    // m,999,path/can have/spaces/and,weirdchars!too.
-    // where n/N/s/S/m/M is sort order, and int is frequency
-    //
-    // prepend new fields to beginning, and read from end, eg:
-    // chunks = line.split(,)
-    // path = chunks[-1]
-    // freq = chunks[-2]
-    // sort = chunks[-3]
-    history_filepath: PathBuf,
-    // model.history should be like
-    // HashMap<String=path, (usize=frequency, FileAttribute=sortoder)>
-    // Maybe read history_filepath later to keep startup quick?
-    //
-    history: HashMap<HistoryPath, HistoryFrequency>,
-}
-
-struct Entry {
-    path: PathBuf,
-    is_dir: bool,
-    name: FileName,
-    // justification for maybe(date) and maybe(size):
-    // dirs dont have filesize - maybe count num of items in dir and display that instead? even recursively? but for now, dir has filesize None
-    // also for permission errors or special folders like $recyclebin - probably wont give you
-    // any metadata about size or date modified
-    // so entries might have filedate None
-    size: Option<FileSize>,
-    date: Option<FileDate>, 
-}
-
-enum Mode {
-    Filter,
-    Normal,
-    // CommandPalette,
-}
-
-// change state and do side effects
-enum Action {
-    GotoDir(PathBuf),
-    SetFilterText(String),
-    SelectEntryUnderCursor,
-    StartFilterMode,
-    ChangeSortOrder(EntryAttribute),
-    ReverseSort,
-    // StartCommandPaletteMode,
-    TryCursorMoveUp,
-    TryCursorMoveDown,
-    Noop,
-    Quit,
-}
-
-#[derive(Eq, Hash, PartialEq, Debug)]
-struct HistoryRecord {
-    path: String,
-    frequency: usize,
-    // preferred_sort, or last_sort: SortBy,
-}
-
-struct FailedToReadHistory;
-struct FailedToWriteHistory;
-
-#[derive(Copy, Clone, PartialEq)]
-enum EntryAttribute {
-    Name,
-    Size,
-    Date,
-}
-
-#[derive(Copy, Clone)]
-struct SortBy {
-    attribute: EntryAttribute,
-    ascending: bool,
-}
-
-#[derive(PartialEq, Eq, Clone)]
-struct FileName(String);
-
-#[derive(PartialEq, Clone)]
-struct FileSize(u64);
-
-#[derive(PartialEq, Clone)]
-struct FileDate(DateTime<Local>);
-
-struct ListViewData {
-    items: Vec<Entry>, // Vec<String> row of Entry, row of Command; could be anything stringified
-                       // maybe try Vec<ListItem> where ListItem impls display() and on_enter()
-    first_viewable_index: usize,
-    cursor_index: usize,
-    max_items_visible: usize,
-    // last_viewable_index = math.min (items.length - 1) , (max_items_visible - first_index)
-    // for later: attrs like marked_indexes:Set, 
-}
-
-// --- associated behavior for data structures --- //
-
-impl Ord for FileName {
-    fn cmp(&self, other: &Self) -> Ordering {
-        let a = &self.0;
-        let b = &other.0;
-        // bad: assumes .chars() will work with unicode filenames
-        // and assumes filename has at least one character
-        let a_is_dir = a.chars().last().unwrap() == '/';
-        let b_is_dir = b.chars().last().unwrap() == '/';
-
-        match (a_is_dir, b_is_dir) {
-            (true, false) => Ordering::Greater,
-            (false, true) => Ordering::Less,
-            _ => b.to_string().to_lowercase().cmp(&a.to_string().to_lowercase())
-        }
-    }
-}
-
-impl PartialOrd for FileName {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-impl Clone for Entry {
-    fn clone(&self) -> Entry {
-        Entry {
-            path: self.path.clone(),
-            is_dir: self.is_dir.clone(),
-            name: self.name.clone(),
-            size: self.size.clone(),
-            date: self.date.clone(),
-        }
-    }
-}
-
-impl PartialEq for Entry {
-    fn eq(&self, other: &Self) -> bool {
-        self.path == other.path
-    }
-}
-
-impl Eq for Entry {}
-
-impl Hash for Entry {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.path.hash(state);
-    }
-}
-
-impl Display for FileName {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        write!(f, "{}", self.0)
-    }
-}
-
-impl Display for FileSize {
-    // formatting options to explore:
-    //
-    // 1) two sig figs / digits, like
-    // 0 - 99 B
-    // 0.1 - 9.9, 10 - 99
-    // KB, MB, GB
-    // it has max 6 char widths
-    //   4 B
-    // 1.5 KB
-    //  27 MB
-    // 0.9 GB
-    //
-    // 2) or 3 sig figs, like
-    // 0-999 B
-    // 1.00 - 9.99
-    // 10.0 - 99.9
-    // 100 - 999
-    // KB, MB, GB
-    // it has max 7 char widths
-    //    1 B
-    //  514 KB
-    // 87.2 MB
-    // 2.31 GB
-    //
-    // 2.5) or just dont use decimals at all
-    //
-    // 3) copy how ls -lh does it
-    //
-    // 4) use standard behavior from bytes crate, like
-    // let byte = byte_unit::Byte::from_bytes(self.0.into()).get_appropriate_unit(false);
-    // "999.99 GB"  "1 B"
-    // which is max 9 chars, min 3. not sure of the formatting alogorithm tho.
-    //
-    // 5) do like windows file explorer, everything just in kb...
-    // 78,696 KB
-    // 890 KB
-    // 7 KB
-    //
-    // 6) always go with 1 decimal precision
-    // 7 max char widths
-    // 4 B
-    // 1.5 K
-    // 900.1 M
-    // 27.9 G
-    // which is good to be more consistent and therefore more readable than varying decimal places
-    //
-    // also note KB vs kb vs KiB vs Kb vs kB... just go with the all caps powers of 10 i think
-
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        const SUFFIXES : [&str; 4] = ["G", "M", "K", "B"];
-        const MAGNITUDES : [u64; 4] = [
-            u64::pow(10,9),
-            u64::pow(10,6),
-            u64::pow(10,3),
-            1
-        ];
-
-        let num_bytes = self.0;
-        match num_bytes {
-            b if b < u64::pow(10,3) => { 
-                write!(f, "{} B", num_bytes)
-            },
-            b if b >= u64::pow(10,12) => {
-                write!(f, "over 1 TB")
-            },
-            _ => {
-                let mut i = 0;
-                while MAGNITUDES[i] > num_bytes {
-                    i += 1;
-                }
-                write!(f, "{:.1} {}", num_bytes as f64 / MAGNITUDES[i] as f64, SUFFIXES[i])
-            },
-        }
-    }
-}
-
-impl Display for FileDate {
-    // options for formatting last modified date:
-    //
-    // 1) either go midnight commander style, showing time if this year else year for prior year
-    // which is 12 chars. eg. if now is july 2022, examples include
-    // Jan  1 23:59
-    // Jan  1 23:59
-    // Dec 31  2021
-    // Jun 15 12:00
-    // although it hurts to lose 24h time when new year begins (eg. starting jan 2022, dec 2021
-    // hours will be hidden)
-    //
-    // 1.5) shorten above a little bit by abbreviating year
-    // and i guess abbreviating hour too
-    // which is 10ch, like 
-    // Dec 31 '21
-    // Jan  1 +23
-    // Jun 12 +12
-    //
-    // 2) or even like imperium, disp how long since modified, max 4 char widths
-    // 8d.      8 d
-    // 5hr.     5 h
-    // 1min.    1 m
-    // 2mo.     2 M
-    // 1yr.     1 y
-    // 19d.    19 d
-    // aka
-    // 1-59 m
-    // 1 - 23 h
-    // 1 - 29? d
-    // 1 - 11 M
-    // 1 - 99? y
-    // bad tho because id like to see finer-grained differences, like 8d vs 8d12h
-    //
-    // 3) "10/10/10 10:10 PM"  "1/01/01 1:01 AM"
-    // max 17 char widths, min 15
-    // %-m/%-d/%y %-I:%M %p
-    //
-    // 4) "10/10/10" max 9 chars
-    // %-m/%-d/%y
-    
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        let now = Local::now();
-        let modified = self.0;
-        let format = match now.year() == modified.year() {
-            // https://docs.rs/chrono/latest/chrono/format/strftime/index.html
-            true => "%b %e %k:%M",
-            false => "%b %e  %Y",
-        };
-        write!(f, "{}", modified.format(format))
-    }
-}
-
-// width of attribute columns, assuming ascii chars
-// based on desired formatted output and what looks nice imo.
-// used in view functions later on
-const SIZE_COLUMN_WIDTH : usize = 7;
-const DATE_COLUMN_WIDTH : usize = 14;
-const MARGIN_WIDTH : usize = 2;
-const MARGIN : &str = "  ";
-const NUM_ROWS_OUTSIDE_LISTVIEW : usize = 6;
-
-impl ListViewData {
-    // fn new(items: Vec<Entry>) {
-    //     return Self {
-    //         items: items,
-    //         first_viewable_index: 0,
-    //         cursor_index: 0,
-    //         max_items_visible
-    //     }
-    // }
-    fn reset_with_items(&mut self, items: Vec<Entry>) {
-        self.items = items;
-        self.cursor_index = 0;
-        self.first_viewable_index = 0;
-    }
-    // fn increment_cursor(&self) -> ListViewData {
-    // return Self {
-    //  ..self,
-    //  cursor_index: new_value
-    // }
-    fn increment_cursor(&mut self) {
-        let last_viewable_index = self.max_items_visible + self.first_viewable_index - 1;
-
-        // at last index, no movement possible
-        if self.cursor_index == self.items.len() - 1 {
-            // noop
-        }
-        // at bottom of list, and you can scroll down
-        else if self.cursor_index == last_viewable_index {
-            self.cursor_index += 1;
-            self.first_viewable_index += 1;
-        }
-        // in middle of list, no need to scroll yet
-        else {
-            self.cursor_index += 1;
-        }
-    }
-    fn decrement_cursor(&mut self) {
-        // at first index, no movement possible
-        if self.cursor_index == 0 {
-            // noop
-        }
-        // at top of list, when scrolling is possible
-        else if self.cursor_index == self.first_viewable_index {
-            self.cursor_index -= 1;
-            self.first_viewable_index -= 1;
-        }
-        // middle of list, no need to scroll yet
-        else {
-            self.cursor_index -= 1;
-        }
-    }
-    // to listen for cursor move up:
-    //   if cursor == 0 then no op
-    //   elif cursor == first index then first index --, and last index --, and cursor --
-    //   else cursor --
-    // and listen for cursor move down:
-    //   if cursor == list length then no op
-    //   elif cursor == last index then first index++, last index ++, and cursor ++
-    //   else cursor ++
-
-    fn set_max_height(&self, num_rows: usize) {
-    }
-    // for later: fn toggle_mark_under_cursor() {
-}
-
-impl SortBy {
-    pub fn compare_entries(&self, a: &Entry, b: &Entry) -> Ordering {
-        match &self.attribute {
-            EntryAttribute::Name => {
-                if a.is_dir && !b.is_dir {
-                    return Ordering::Greater;
-                }
-                if !a.is_dir && b.is_dir {
-                    return Ordering::Less;
-                }
-                return b.name.to_string().to_lowercase().cmp(&a.name.to_string().to_lowercase());
-            },
-            EntryAttribute::Size => {
-                match (&a.size, &b.size) {
-                    (None, Some(b)) => Ordering::Less,
-                    (Some(a), None) => Ordering::Greater,
-                    (Some(a), Some(b)) if a.0 < b.0 => Ordering::Less, 
-                    (Some(a), Some(b)) if a.0 > b.0 => Ordering::Greater,
-                    _ => Ordering::Equal,
-                }
-            }
-            EntryAttribute::Date => {
-                match (&a.date, &b.date) {
-                    (None, Some(b)) => Ordering::Less,
-                    (Some(a), None) => Ordering::Greater,
-                    (Some(a), Some(b)) if a.0 < b.0 => Ordering::Less, 
-                    (Some(a), Some(b)) if a.0 > b.0 => Ordering::Greater,
-                    _ => Ordering::Equal,
-                }
-            }
-        }
-    }
-}
-
-impl std::fmt::Debug for Entry {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.name)
-    }
-}
-
-impl std::convert::From<DirEntry> for Entry {
-    // are there permission errors that could happen here?
-    fn from(oldentry: DirEntry) -> Self {
-        let path = oldentry.path();
-        // TOOD: learn what filename errors are possible, then handle them
-        let mut name = path.file_name().unwrap().to_str().unwrap().to_string();
-        let is_dir = path.is_dir();
-        let mut size_bytes = None;
-        let mut date_modified = None;
-        match oldentry.metadata() {
-            Ok(metadata) => {
-                size_bytes = Some(FileSize(metadata.len()));
-                date_modified = match metadata.modified() {
-                    // apparently some platforms do not have mtime / ftLastWriteTime available
-                    // https://doc.rust-lang.org/std/fs/struct.Metadata.html#errors
-                    Ok(system_time) => {
-                        Some(FileDate(DateTime::<Local>::from(system_time)))
-                    },
-                    Err(err) => None,
-                };
-            },
-            Err(err) => (),
-        };
-        if is_dir {
-            name = format!("{}/", name);
-            // dir.metadata.len just returns size of os-dir file object thingy, which is not useful
-            // its not actually related to size of contents
-            size_bytes = None;
-        }
-        Self {
-            path: path,
-            is_dir: is_dir,
-            name: FileName(name),
-            size: size_bytes,
-            date: date_modified,
-        }
-    }
-}
-
-impl std::convert::From<std::io::Error> for FailedToReadHistory {
-    fn from(err: std::io::Error) -> Self { FailedToReadHistory }
-}
-
-impl std::convert::From<std::num::ParseIntError> for FailedToReadHistory {
-   fn from(err: std::num::ParseIntError) -> Self { FailedToReadHistory }
-}
-
-impl std::convert::From<std::io::Error> for FailedToWriteHistory {
-    fn from(err: std::io::Error) -> Self { FailedToWriteHistory }
-}
-
-
-impl Display for HistoryRecord {
-    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        // example: "1,my/path"
-        write!(f, "{},{}", self.frequency, self.path)
-    }
-}
-
-impl std::convert::TryFrom<String> for HistoryRecord {
-    type Error = FailedToReadHistory;
-
-    fn try_from(record_string: String) -> Result<Self, Self::Error> {
-        // example of expected record_string: "1,my/path"
-        let mut chars = record_string.chars();
-        let frequency : usize = chars
-            .by_ref()
-            .take_while(|&c| c != ',')
-            .collect::<String>()
-            .parse()?;
-
-        let path : String = chars
-            .by_ref()
-            .collect::<String>();
-        Ok( Self { path: path, frequency: frequency} )
-    }
-}
-
-fn read_directory_contents_into_sorted(dir: &PathBuf, sort: SortBy) -> Vec<Entry> {
-    // optimization idea: replace this fn with
-    // read_directory_quickly(dir, sort) -> Vec<PathBuf> 
-    // only gets name of entries for an in-progress view, and avoids reading metadata
-    // TODO - measure time to read metadata.size/date
-    // also TODO - measure to confirm binary heap is actually faster than simple vec
-
-    let mut name_sorted_heap = BinaryHeap::new_by(|a: &Entry, b: &Entry| sort.compare_entries(a, b) );
-    
-    match dir.read_dir() {
-        Ok(dir_entries) => {
-            for direntry in dir_entries {
-                match direntry {
-                    Ok(e) => name_sorted_heap.push(Entry::from(e)),
-                    // todo: give better msg if io err happens getting dir entry
-                    Err(err) => (),
-                };
-            }
-        },
-        // todo: give better msg if io err reading dir
-        Err(err) => (),
-    };
-
-    // TODO - reverse this if !sort.ascending
-    // return name_sorted_heap.into_sorted_vec();
-    return name_sorted_heap.into_sorted_vec().into_iter().rev().collect();
-}
-
-fn sort_entries(entries: &Vec<Entry>, sort: SortBy) -> Vec<Entry> {
-    // let mut new_entries = entries.into_iter().collect::<Vec<&Entry>>();
-    let mut new_entries = entries.clone();
-    new_entries.sort_by(|a,b| sort.compare_entries(&a, &b));
-    if !sort.ascending {
-        new_entries.reverse();
-    }
-    return new_entries;
-}
-
-// type HistoryRecord = (String, usize);
-type HistoryPath = String;
-type HistoryFrequency = usize;
-
-fn read_history_file(filename: &PathBuf) -> Result<HashMap<HistoryPath, HistoryFrequency>, FailedToReadHistory> {
-    // input:
-    // 123,/my/path
-    // 45,/another/path
-    // let mut history = HashSet::<HistoryRecord>::new();
-    let mut history = HashMap::<HistoryPath, HistoryFrequency>::new();
-
-    let file = File::open(filename)?;
-
-    // line reading taken from:
-    // https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html 
-    for line in BufReader::new(file).lines() {
-        // hope reading line is Ok,
-        // and hope parsing line in HistoryRecord::from is Ok
-        // else return Err type of this function
-        let record = HistoryRecord::try_from(line?)?; 
-        history.insert(record.path, record.frequency);
-    }
-    Ok(history)
-}
-
-fn write_history_file(history: HashMap<HistoryPath, HistoryFrequency>, filepath: PathBuf) -> Result<(), FailedToWriteHistory> {
-    // output:
-    // 123,/my/path
-    // 45,/another/path
-    let mut file = std::fs::OpenOptions::new()
-        .write(true)
-        .create(true)
-        .open(&filepath)?;
-
-    let line_separated_records = history
-        .iter()
-        .map( |(path, freq)| 
-            HistoryRecord {
-                path: path.to_string(),
-                frequency: *freq // from borrowed primitive usize to copied/cloned usize
-            }.to_string()
-        )
-        .collect::<Vec<String>>()
-        .join("\n");
-
-    file.write(line_separated_records.as_bytes())?;
-    Ok(())
-}
-
-fn increment_history(history: &mut HashMap<HistoryPath, HistoryFrequency>, path: HistoryPath) {
-    history
-        .entry(path)
-        .and_modify( |freq| *freq += 1 )
-        .or_insert(1);
-}
-
-// --- UPDATES AND APP LOGIC --- //
-
-fn main() {
-    let program_result = Program {init, view, update}.run();
-    match program_result {
-        Ok(model) => {
-            write!(std::io::stdout(), "{}", model.cwd.display());
-            write_history_file(model.history, model.history_filepath);
-            // maybe should write_history_file on every cwd update?
-        },
-        Err(msg) => {
-            write!(std::io::stderr(), "Error: {}", msg.to_string());
-        }
-        // TODO - on err, still write cwd to stdout so parent script won't fail
-    };
-}
-
-fn init() -> Result<Model, String> {
-    log::set_logger(&LOGGER)
-        .map(|()| log::set_max_level(log::LevelFilter::Info));
-
-    log::info!("---\nnew session");
-
-    let cwd = std::env::current_dir().unwrap();
-    let sort = SortBy{ attribute: EntryAttribute::Name, ascending: false };
-    let sorted_entries = read_directory_contents_into_sorted(&cwd, sort);
-    // let (cols, rows) = terminal::size()?;
-    let (cols, rows) = match terminal::size() {
-        Ok((cols, rows)) => (usize::from(cols), usize::from(rows)),
-        Err(_) => return Err("can't read terminal size".to_string()),
-    };
-    let list_view = ListViewData {
-        items: sorted_entries.clone(),
-        first_viewable_index: 0,
-        cursor_index: 0,
-        max_items_visible: rows - NUM_ROWS_OUTSIDE_LISTVIEW,
-    };
-
-
-    // Thoughts on dotfiles, env vars, and related conventions:
-    //
-    // I personally don't like the XDG basedir spec (https://specifications.freedesktop.org/basedir-spec/basedir-spec-latest.html)
-    //
-    // 1) the XDG spec defines several directories, including:
-    //    $XDG_DATA_HOME, XDG_CONFIG_HOME, STATE_HOME
-    //    According to the spec, fmin should use most of those dirs
-    //    (write selected entries to STATE_HOME/.fmin_selected_filepaths,
-    //     keep history in DATA_HOME/.fmin_history, 
-    //     config file at CONFIG_HOME/.fminrc,
-    //     and use CACHE_HOME if I ever get that serious)
-    //    Too many directories for a tiny tool like fmin, imo.
-    //    Twould be like having `ls` vomit four files all over your system.
-    //    Not to mention my personal preference for portable, self-contained app directories.
-    //    But that's just me.
-    //
-    // 2) the XDG spec also defines fallbacks for each env var, eg. 
-    //    "if $XDG_CONFIG_HOME is not set, a default equal to $HOME/.config should be used."
-    //    Which means the spec defines what to do if you don't follow the spec...
-    //    Or in other words, you can't fully support the XDG spec and non-XDG fallbacks 
-    //      at the same time.
-    //    That's a less-important feature of the spec, tho. Easy to ignore.
-    //
-    // 3) Some big old command-line tools have publicly stated they will never 
-    //    break compatibility to support XDG; take $HOME/.bashrc, for example.
-    //    Other tools half-heartedly support the XDG spec, 
-    //      and just dump everything to $XDG_CONFIG_HOME if it's set,
-    //        and hope that's enough to satisfy XDG-enjoyers.
-    //    Still other tools don't even bother with the XDG spec.
-    //    Very few implement the spec 100%.
-    //
-    // I think users mainly care about keeping $HOME clean
-    // and keeping config files in one place / easy to backup.
-    //
-    // But supporting the XDG spec is only a few extra LOC. So I might as well do it.
-    // For now, that means putting .fmin_history in $XDG_DATA_HOME if it's set.
-    //
-    // - fmin provides an alternative data home var like $FMIN_HOME,
-    //   meant for the users that want neither XDG nor naked $HOME.
-    //   I'm not sure if every tool should have a custom env var home,
-    //   but it feels reasonable to me for now.
-    //   Everything goes in one folder, which is at worst easy to understand and control
-    //
-    // - if those env vars are unset, then do the typical $HOME/.dotfile behavior,
-    //   because everyone is used to that,
-    //   and picky people should have developed workarounds by now.
-    //
-    // Ultimately, there will always be tools with dotfiles in $HOME,
-    // and there will always be tools half-heartedly implementing the XDG spec,
-    // whether or not anyone likes it, and whether or not it's a good spec.
-    //
-    // So I think the best behavior is educating users,
-    // embracing long-running conventions instead of breaking backwards-compatiblity,
-    // giving users an escape hatch for customization and control over any dotfile
-    //   (see symlinks,
-    //    and symlink management tools like gnu stow,
-    //    and other config-mangement options like
-    //    leaving ~/ for config while using another dir like ~/mycleanhome/ for personal use,
-    //    or perhaps using cli options --config if possible)
-    // and minimizing the amount of config needed in the first place.
-
-    let data_dir = match(
-        std::env::var("FMIN_HOME"),
-        std::env::var("XDG_DATA_HOME"),
-        std::env::var("HOME"), // TODO - replace with windows env var for home? %USERHOME% or whatver?
-    ) {
-        ( Ok(fmin_home), _, _) => PathBuf::from(fmin_home),
-        ( Err(_), Ok(xdg_config_home), _ ) => PathBuf::from(xdg_config_home),
-        ( Err(_), Err(_), Ok(home) ) => PathBuf::from(home).join(".fmin/"),
-        _ => return Err("need to set directory: either $FMIN_HOME, $XDG_DATA_HOME, or $HOME".to_string()),
-    };
-
-    const HISTORY_FILENAME : &str = ".fmin_history";
-    let history_filepath = data_dir.join(HISTORY_FILENAME);
-    let mut history = match read_history_file(&history_filepath) {
-        Ok(records) => records,
-        Err(FailedToReadHistory) => HashMap::<HistoryPath, HistoryFrequency>::new(),
-    };
-    // or later, in Action::GotoMode:
-    // let history = match m.history {
-    //     NotInitialized => read_history_file()
-    //     Loaded(records) => 
-    //     Failed => ...
-    // }
-    log::info!("{:?}", history);
-
-    increment_history(&mut history, cwd.display().to_string());
- 
-    Ok(Model {
-        cwd: cwd,
-        cwd_sort: sort,
-        sorted_entries: sorted_entries,
-        filter_text: "".to_string(),
-        mode: Mode::Filter,
-        cols: cols,
-        rows: rows,
-        list_view: list_view,
-        history_filepath: history_filepath,
-        history: history,
-    })
-}
-
-fn update(m: &mut Model, terminal_event: Event) -> UpdateResult {
-    // exit early if ctrl+c, no matter what
-    // returning None means to quit the program
-    // TODO - have a better return type than None/Some(())
-
-    match terminal_event {
-        Event::Key(keyevent) => {
-            if
-                keyevent.modifiers == KeyModifiers::CONTROL &&
-                keyevent.code == KeyCode::Char('c')
-            {
-                return UpdateResult::Finish;
-            }
-        },
-        Event::Resize(cols, rows) => {
-            m.cols = usize::from(cols);
-            m.rows = usize::from(rows);
-            m.list_view.max_items_visible = m.rows - NUM_ROWS_OUTSIDE_LISTVIEW;
-        },
-        _ => ()
-    };
-    // respond to crossterm event and output an action
-    let action = match m.mode {
-        Mode::Normal => {
-            match terminal_event {
-                Event::Key(keyevent) => {
-                    match keyevent.code {
-                        KeyCode::Char('/') => Action::StartFilterMode,
-                        // KeyCode::Char('>') => Action::StartJumpMode, // maybe @ would work?
-                        // KeyCode::Char('?') => Action::StartCommandPaletteMode,
-                        KeyCode::Backspace => {
-                            match m.cwd.parent() {
-                                Some(path) => Action::GotoDir(path.to_owned()),
-                                None => Action::Noop,
-                            }
-                        },
-                        KeyCode::Char('n') => {
-                            // Action::ChangeSortOrder(SortBy{ 
-                            //     attribute: EntryAttribute::Name,
-                            //     ascending: match m.cwd_sort.attribute == EntryAttribute::Name {
-                            //         EntryAttribute::Name => !m.cwd_sort.ascending,
-                            //         _ => true,
-                            //     }
-                            // }),
-                            match m.cwd_sort.attribute {
-                                EntryAttribute::Name => Action::ReverseSort,
-                                _ => Action::ChangeSortOrder(EntryAttribute::Name),
-                            }
-                        },
-                        KeyCode::Char('s') => {
-                            match m.cwd_sort.attribute {
-                                EntryAttribute::Size => Action::ReverseSort,
-                                _ => Action::ChangeSortOrder(EntryAttribute::Size),
-                            }
-                        },
-                        KeyCode::Char('m') => {
-                            match m.cwd_sort.attribute {
-                                EntryAttribute::Date => Action::ReverseSort,
-                                _ => Action::ChangeSortOrder(EntryAttribute::Date),
-                            }
-                        },
-                        KeyCode::Char('k') | KeyCode::Up => Action::TryCursorMoveUp,
-                        KeyCode::Char('j') | KeyCode::Down => Action::TryCursorMoveDown,
-                        KeyCode::Enter => Action::SelectEntryUnderCursor,
-                        KeyCode::Char('q') => Action::Quit,
-                        _ => Action::Noop,
-                    }
-                },
-                _ => Action::Noop,
-            }
-        },
-        Mode::Filter => {
-            match terminal_event {
-                Event::Key(keyevent) => {
-                    match keyevent.modifiers {
-                        KeyModifiers::SHIFT => match keyevent.code {
-                            KeyCode::Char('K') => Action::TryCursorMoveUp,
-                            KeyCode::Char('J') => Action::TryCursorMoveDown,
-                            KeyCode::Char('N') => match m.cwd_sort.attribute {
-                                EntryAttribute::Name => Action::ReverseSort,
-                                _ => Action::ChangeSortOrder(EntryAttribute::Name),
-                            },
-                            KeyCode::Char('S') => match m.cwd_sort.attribute {
-                                EntryAttribute::Size => Action::ReverseSort,
-                                _ => Action::ChangeSortOrder(EntryAttribute::Size),
-                            },
-                            KeyCode::Char('M') => match m.cwd_sort.attribute {
-                                EntryAttribute::Date => Action::ReverseSort,
-                                _ => Action::ChangeSortOrder(EntryAttribute::Date),
-                            },
-                            // KeyCode::Char('O') => Action::StartJumpMode,
-                            // KeyCode::Char('P') => Action::StartCommandPaletteMode,
-                            KeyCode::Char('Q') => Action::Quit,
-                            _ => Action::Noop,
-                        },
-                        _ => match keyevent.code {
-                            // todo: listen for end-of-input ctrl+d, arrow keys left and right, paste
-                            // ideally a readline lib is used to listen for input here, but
-                            // - dont need history or multiline editing, and probably not vim/emacs shortcuts
-                            // - not sure how to include static .so external dependency
-                            // so probably not worth including that extra dependency
-                            KeyCode::Esc => Action::SetFilterText("".to_string()),
-                            KeyCode::Char(c) => {
-                                Action::SetFilterText(format!("{}{}", m.filter_text, c))
-                            },
-                            KeyCode::Backspace => {
-                                match m.filter_text.is_empty() {
-                                    // if filter text input is empty, backspace will nav back as if in
-                                    // normal mode.
-                                    // this reduces keypresses when navigating since normal mode is not
-                                    // default.
-                                    true => match m.cwd.parent() {
-                                        Some(path) => Action::GotoDir(path.to_owned()),
-                                        None => Action::SetFilterText("".to_string()), // clear and go normal mode
-                                    },
-                                    // remove last char. warning: doesnt account for unicode.
-                                    // but this quick and dirty solution is ok for now.
-                                    // and most filepath inputs will only have ascii anyways.
-                                    false => {
-                                        let mut chars = m.filter_text.chars();
-                                        chars.next_back();
-                                        let all_chars_but_last = chars.as_str().to_string();
-                                        Action::SetFilterText(all_chars_but_last) // potentially clear and go normal mode
-                                    },
-                                }
-                            },
-                            KeyCode::Up => {
-                                Action::TryCursorMoveUp
-                            },
-                            KeyCode::Down => {
-                                Action::TryCursorMoveDown
-                            },
-                            KeyCode::Enter => Action::SelectEntryUnderCursor,
-                            _ => Action::Noop,
-                        }
-                    }
-                },
-                _ => Action::Noop,
-            }
-        },
-    };
-    // update state
-    match action {
-        Action::GotoDir(pathbuf) => {
-            m.cwd_sort = SortBy { attribute: EntryAttribute::Name, ascending: true };
-            m.sorted_entries = read_directory_contents_into_sorted(&pathbuf, m.cwd_sort);
-            m.cwd = pathbuf;
-            m.mode = Mode::Filter;
-            m.list_view.reset_with_items(m.sorted_entries.clone());
-            increment_history(&mut m.history, m.cwd.display().to_string());
-            // m.history = increment_history(m.history, pathbuf);
-            UpdateResult::Continue
-        },
-        Action::SetFilterText(text) => {
-            // m.mode = match text.is_empty() {
-            //     true => Mode::Normal,
-            //     false => Mode::Filter,
-            // };
-            m.filter_text = text;
-
-            let filtered_entries =  m.sorted_entries
-                .clone()
-                .into_iter()
-                .filter(|entry| entry.name.0.to_lowercase().contains(&m.filter_text.to_lowercase()) )
-                .collect::<Vec<Entry>>();
-
-            m.list_view.reset_with_items(filtered_entries);
-            UpdateResult::Continue
-        },
-        Action::SelectEntryUnderCursor => {
-            // if no cursor, cant do anything
-            if m.list_view.items.len() == 0 { return UpdateResult::Continue; }
-
-            let entry = &m.list_view.items[m.list_view.cursor_index];
-
-            if entry.is_dir {
-                m.cwd = entry.path.clone();
-                m.sorted_entries = read_directory_contents_into_sorted(&entry.path, m.cwd_sort);
-                m.mode = Mode::Filter;
-                m.filter_text = "".to_string();
-                m.cwd_sort = SortBy { attribute: EntryAttribute::Name, ascending: true };
-                increment_history(&mut m.history, m.cwd.display().to_string());
-                m.list_view.reset_with_items(m.sorted_entries.clone());
-            }
-            UpdateResult::Continue
-        },
-        Action::ChangeSortOrder(attribute) => {
-            // TODO - consider preserving hovered entry by finding it in the new sorted vec,
-            // and updating cursor_index accordingly,
-            // instead of just resetting to top
-            m.cwd_sort.attribute = attribute;
-            m.cwd_sort.ascending = false;
-            m.sorted_entries = sort_entries(&m.sorted_entries, m.cwd_sort);
-            m.list_view.reset_with_items(m.sorted_entries.clone());
-            UpdateResult::Continue
-        },
-        Action::ReverseSort => {
-            m.cwd_sort.ascending = !m.cwd_sort.ascending;
-            m.sorted_entries = sort_entries(&m.sorted_entries, m.cwd_sort);
-            m.list_view.reset_with_items(m.sorted_entries.clone());
-            UpdateResult::Continue
-        },
-        Action::TryCursorMoveUp => {
-            m.list_view.decrement_cursor();
-            UpdateResult::Continue
-        },
-        Action::TryCursorMoveDown => {
-            m.list_view.increment_cursor();
-            UpdateResult::Continue
-        },
-        Action::StartFilterMode => {
-            m.mode = Mode::Filter;
-            UpdateResult::Continue
-        },
-        Action::Noop => UpdateResult::Continue,
-        Action::Quit => UpdateResult::Finish,
-    }
-}
-
-// --- VIEWS AND MESSY STRING HANDLING --- //
-
-fn view(m: &Model, stderr: &mut std::io::Stderr) {
-    // half-declarative view, without implementing a whole ui framework
-    // hinges on having only one flex span horiz and vert - rest are static sizes
-    //
-    // view must be impure function writing to mutable buf stderr
-    // since crossterm lib puts control bytes in custom types like SetBackgroundColor
-    // so view happens with mutable in queue!(buf, ...) function
-    // and not postponed for agnostic model/update/view loop
-    //
-    // maybe i can send a list of crossterm::Commands to queue...
-    // but probably not worth making a whole structure of dozens of commands, 
-    // only to delay writing to the same place one function later,
-    // just for the sake of 'purity'
-
-    // mockup:
-    //
-    //  C:\users\jkwon\desktop\programming\modenv
-    //  ___________________________________________
-    //   Name                v | Size   | Modified
-    //  ___________________________________________
-    //  loopy/                   12       2022-06
-    // *droopy/                  4        2022-06
-    // *grumpy/                  0        2022-06
-    //  frumpy/                  99       2022-06
-    // *script1.py               92 KB    2022-06
-    //  script_2.py              108 MB   2022-07
-    //  main.py                  1.2 GB   2022-05
-    //  utils.py                 985 B    2021-12
-    // 
-    //  (mode) :?!@>/someinputtext        item 2 of 20, and 3 selected
-    //  ___________________________________________
-    //
-    //
-    //  consider the sentence as a UI element, as proposed in the essay magic ink (i just read it)
-    //  also consider inspiration from other file manager status lines like:
-    //  https://raw.githubusercontent.com/ranger/ranger-assets/master/screenshots/multipane.png
-    
-    let divider : &str = &"-".repeat(m.cols);
-    let spacer : &str = &" ".repeat(m.cols);
-    #[macro_export]
-    macro_rules! divider {
-        () => {
-            queue!(stderr, Print(divider), MoveToNextLine(1));
-        };
-    }
-    #[macro_export]
-    macro_rules! empty_line {
-        () => {
-            queue!(stderr, Print(spacer), MoveToNextLine(1));
-        };
-    }
-    queue!(stderr, crossterm::cursor::Hide);
-
-    view_cwd(m, stderr);            // height = 1 // 2
-    divider!();                     // height = 1
-    view_column_headers(m, stderr); // height = 1
-    divider!();                     // height = 1
-    view_list_body(m, stderr);      // height = m.rows - 8
-    // empty_line!();                  // height = 1
-    divider!();                     // height = 1
-    view_footer(m, stderr);         // height = 1
-    // empty_line!();                  // height = 1
-}
-
-fn view_cwd(m: &Model, stderr: &mut std::io::Stderr) {
-    queue!(stderr,
-           MoveTo(0,0),
-           // Print(" ".repeat(m.cols)), 
-           // MoveToNextLine(1),
-           fit(&format!(" {}", m.cwd.display()), m.cols),
-           MoveToNextLine(1)
-    );
-}
-
-fn view_column_headers(m: &Model, stderr: &mut std::io::Stderr) {
-    let name_header = format!(" Name {}", sort_indicator(EntryAttribute::Name, m.cwd_sort));
-    let size_header = format!("Size {} ", sort_indicator(EntryAttribute::Size, m.cwd_sort));
-    let date_header = format!("  Modified {}  ", sort_indicator(EntryAttribute::Date, m.cwd_sort));
-    queue!(stderr, 
-           fit(&name_header, m.cols - SIZE_COLUMN_WIDTH - DATE_COLUMN_WIDTH - MARGIN_WIDTH),
-           Print(MARGIN),
-           fit(&size_header, SIZE_COLUMN_WIDTH),
-           fit(&date_header, DATE_COLUMN_WIDTH),
-           MoveToNextLine(1)
-    );
-}
-
-fn view_list_body(m: &Model, stderr: &mut std::io::Stderr) {
-    // example of displaying list_view.items and indexes:
-    //
-    // all items indexes  
-    // on left
-    //                    
-    // out of   0 
-    // view     -----    viewable indexes
-    //          1   0    on right           
-    //          2   1           
-    //          3   2  
-    //          4   3
-    //          -----
-    // out of   5
-    // view
-    //
-    // first_viewable_index = 1
-    // max_items_visible = 4
-
-    let viewable_entries = m.list_view.items.iter()
-        .skip(m.list_view.first_viewable_index)
-        .take(m.list_view.max_items_visible); 
-
-    for (visible_index, entry) in viewable_entries.enumerate() {
-        let name = &entry.name.0.clone();
-        let size = match &entry.size {
-            Some(size_bytes) => size_bytes.to_string(),
-            None => String::new(),
-        };
-        let date = match &entry.date {
-            Some(date_modified) => date_modified.to_string(),
-            None => String::new(),
-        };
-
-        let at_cursor = m.list_view.cursor_index == visible_index + m.list_view.first_viewable_index;
-        if at_cursor { queue!(stderr, SetBackgroundColor(Color::DarkGrey)); }
-
-        queue!(stderr,
-               Print(" "),
-               fit(&name, m.cols - SIZE_COLUMN_WIDTH - DATE_COLUMN_WIDTH - 2 * MARGIN_WIDTH),
-               Print(MARGIN),
-               fit( &pad_align_right(&size, SIZE_COLUMN_WIDTH), SIZE_COLUMN_WIDTH),
-               Print(MARGIN),
-               fit(&date, DATE_COLUMN_WIDTH),
-               MoveToNextLine(1),
-        );
-
-        if at_cursor { queue!(stderr, ResetColor); }
-    }
-
-    // draw over any empty rows
-    if m.list_view.max_items_visible > m.list_view.items.len() {
-        let empty_rows = m.list_view.max_items_visible - m.list_view.items.len();
-
-        for _ in 0..empty_rows {
-            queue!(stderr, Print(" ".repeat(m.cols)), MoveToNextLine(1));
-        }
-    }
-}
-
-fn view_footer(m: &Model, stderr: &mut std::io::Stderr) {
-    queue!(stderr, 
-           // clear any artifacts from previous draw
-           Print(" ".repeat(m.cols)),
-           MoveToColumn(1),
-           // display filter field
-           Print(&format!(" {} {}",
-                        match m.mode {
-                            Mode::Filter => "(filter)",
-                            Mode::Normal => "(normal)",
-                        },
-                        match m.mode {
-                            Mode::Filter => format!(" /{}", m.filter_text),
-                            _ => String::new(),
-                        },
-                        ),
-               ),
-    );
-    match m.mode {
-        Mode::Filter => queue!(stderr, crossterm::cursor::Show,),
-        _ => queue!(stderr, crossterm::cursor::Hide,),
-    };
-}
-
-// --- view helpers --- //
-
-fn sort_indicator(match_attribute: EntryAttribute, current_sort: SortBy) -> &'static str {
-    if match_attribute != current_sort.attribute { return " "; }
-
-    return match current_sort.ascending {
-        true => "^",
-        false => "v",
-    };
-}
-
-fn fit(s: &str, final_length: usize) -> Print<String> {
-    Print(fit_to_length(s, final_length))
-}
-
-fn fit_to_length(s: &str, final_length: usize) -> String {
-    // bad: assuming only ASCII input...
-    match str_length(s) {
-        // too short
-        length if length <= final_length => {
-            pad_align_left(s, final_length)
-        },
-        // too long
-        length => {
-            s.chars().take(final_length).collect::<String>()
-        }
-    }
-}
-
-fn str_length<S: AsRef<str>> (s: S) -> usize {
-    // bad: assuming only ASCII input...
-    return s.as_ref().chars().count();
-}
-
-fn pad_align_left(s: &str, final_length: usize) -> String {
-    // pad string$0 with : spaces, left aligned <, to meet final_length$1
-    format!("{0: <1$}", s, final_length) 
-}
-
-fn pad_align_right(s: &str, final_length: usize) -> String {
-    format!("{0: >1$}", s, final_length) 
-}
+// fmin, a terminal file manager inspired by fman + vim
+
+#![allow(unused_variables)]
+#![allow(unused_imports)]
+
+use std::cell::RefCell;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashSet, HashMap};
+use std::fmt::{Display, Formatter, Error};
+use std::fs::{DirEntry, File};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant, SystemTime};
+
+use binary_heap_plus::BinaryHeap;
+use chrono::{DateTime, Datelike, TimeZone, Local};
+use crossterm::{
+    terminal,
+    queue,
+    execute,
+    cursor::{MoveTo, MoveToColumn, MoveToRow, MoveToNextLine, MoveToPreviousLine},
+    style::{Print, Color, SetBackgroundColor, SetForegroundColor, ResetColor},
+    event::{
+        read as await_next_event, 
+        Event, 
+        KeyCode, 
+        KeyEvent, 
+        KeyModifiers,
+    },
+};
+use log::{info};
+
+use crate::config::{Config, Opener};
+use crate::dir_cache::DirCache;
+use crate::dir_loader::{LoadedMetadata, MetadataLoader};
+use crate::journal::{JournalBatch, JournalOp};
+use crate::pager::Pager;
+use crate::preview::{Preview, Previewer};
+use crate::watcher::Watcher;
+use crate::tui_program::{Program, ProgramEvent, UpdateResult};
+use crate::worker::{Job, RunningJob};
+
+mod tui_program;
+mod file_ops;
+mod trash;
+mod journal;
+mod config;
+mod worker;
+mod opener;
+mod preview;
+mod pager;
+mod dir_loader;
+mod dir_cache;
+mod watcher;
+
+// --- for debugging
+
+struct LoggerToFile<'a> {
+    path: &'a str,
+}
+
+impl log::Log for LoggerToFile<'_> {
+    fn flush(&self) {}
+    fn enabled(&self, metadata: &log::Metadata) -> bool { true }
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(self.path)
+                // not sure what to do when log file breaks
+                // either silently fail or crash. i choose crash
+                .expect("failure to open or read log file");
+            // nowhere left to report it if logging itself fails
+            let _ = writeln!(file, "{}", record.args());
+        }
+    }
+}
+
+static LOGGER : LoggerToFile = LoggerToFile { path: "/tmp/fmin_log" };
+
+// --- MODEL, and other data structures --- //
+
+struct Model {
+    mode: Mode,
+    cwd: PathBuf,
+    cwd_sort: SortBy,
+    // small optimization i think: sort entries at cd time, then filter from the sorted list on
+    // each keypress
+    // rather than sort from filtered list every keypress
+    // should calculate and store filtered and sorted entries at cd time
+    // so that less work is required during render time
+    // all_entries -> sort -> filter -> viewable slice of entries
+    // all_entries: HashSet<Entry>,
+    sorted_entries: Vec<Entry>,
+    // marked by path instead of index, so marks survive the list changing underneath them,
+    // like when typing filter text
+    marked_paths: HashSet<PathBuf>,
+    // where a range of marks starts, like shift+click
+    mark_anchor: Option<PathBuf>,
+    // reading the size and date of each entry in the cwd, while the names are already on screen
+    metadata_loader: Option<MetadataLoader>,
+    // dirs visited recently, so going back to one doesn't mean reading it all over again
+    dir_cache: DirCache,
+    // the cwd's mtime from just before its entries were read, for caching them once they're all loaded
+    cwd_modified: Option<SystemTime>,
+    // for background threads to send their results back
+    messages: Sender<Message>,
+    // refreshes the cwd when other programs change it
+    watcher: Watcher,
+    // view() only gets &Model, but remembering what it's formatted saves redoing it every frame
+    row_cells: RefCell<RowCells>,
+    // rows that were just added or changed by a refresh, and when. shown highlighted for a moment
+    recently_changed: HashMap<PathBuf, Instant>,
+    // what the cwd looked like before a refresh, for telling what changed once the metadata's in
+    entries_before_refresh: Option<HashMap<PathBuf, Entry>>,
+    filter_text: String,
+    cols: usize,
+    rows: usize,
+    list_view: ListViewData<Entry>,
+    // model.history_filepath file contents look like:
+    // #fmin_history 3
+    // -,n,1700000000,1,my/path
+    // p,S,1690000000,20,other/path
+    // -,-,-,999,path/can have/spaces/and,weirdchars!too.
+    // where p means pinned (- if not), then n/N/s/S/m/M is sort order (- if never changed),
+    // then last visit as unix seconds (- if unknown), then int frequency, then path
+    //
+    // new fields get prepended to the beginning, and the version in the header line says how many
+    // fields come before the path, eg:
+    // chunks = line.splitn(num_fields_in_version, ',')
+    // path = chunks[-1], which is safe even if the path has commas
+    // freq = chunks[-2]
+    // last_visit = chunks[-3]
+    // sort = chunks[-4]
+    // pinned = chunks[-5]
+    //
+    // files without a header are the original version 1, with only "freq,path"
+    history_filepath: PathBuf,
+    // model.history should be like
+    // HashMap<String=path, (usize=frequency, FileAttribute=sortoder)>
+    // Maybe read history_filepath later to keep startup quick?
+    //
+    history: HashMap<HistoryPath, HistoryData>,
+    history_changes: Vec<HistoryChange>,
+    // jump mode has its own input text and list, separate from the cwd filter,
+    // so leaving jump mode puts you right back where you were
+    jump_text: String,
+    jump_list: ListViewData<JumpCandidate>,
+    // checked once when entering jump mode, not on every keypress,
+    // since stat-ing unplugged drives or network mounts can be slow
+    missing_dirs: HashSet<HistoryPath>,
+    // the palette lists what you can do from the mode it was opened in,
+    // and runs the chosen command as if it came from that mode
+    palette_origin: Mode,
+    palette_text: String,
+    palette_list: ListViewData<PaletteCommand>,
+    custom_commands: Vec<CustomCommand>,
+    // the open-with list, for the file that was under the cursor when it opened.
+    // like the palette, it goes back to the mode it was opened from
+    open_with_origin: Mode,
+    open_with_path: PathBuf,
+    open_with_text: String,
+    // every choice, before filtering by open_with_text
+    open_with_choices: Vec<Opener>,
+    open_with_list: ListViewData<Opener>,
+    // the last open-with choice for each extension
+    open_with_filepath: PathBuf,
+    // where fmin keeps its files, like history and the files that custom commands read
+    data_dir: PathBuf,
+    // file operations that can be undone, shared with other sessions
+    journal_filepath: PathBuf,
+    config: Config,
+    // one line of feedback, like the output of a custom command. cleared on the next keypress
+    status_message: String,
+    // entries waiting to be pasted, which can be from any dir
+    yank_register: Option<YankRegister>,
+    // an action waiting for y/n, which takes over the footer and every keypress until answered
+    pending_confirmation: Option<Confirmation>,
+    // a text input like "new folder name:", which also takes over the footer and every keypress
+    prompt: Option<Prompt>,
+    // a copy, move, or delete happening on another thread. only one at a time
+    running_job: Option<RunningJob>,
+    // set while a program like $EDITOR has the terminal
+    after_child_exits: Option<AfterChildExits>,
+    // the right-hand column, showing what's inside the entry under the cursor
+    preview_visible: bool,
+    previewer: Previewer,
+    // filled in as the previewer gets to them. an entry's is dropped when the entry changes
+    // each with when it was loaded, so the oldest can make room for a new one
+    previews: HashMap<PathBuf, (Preview, Instant)>,
+    // asked for but not back yet, so it's only asked for once
+    preview_requested: Option<PathBuf>,
+    // the file being viewed in pager mode, which goes back to the mode it was opened from
+    pager: Option<Pager>,
+    pager_origin: Mode,
+}
+
+struct Entry {
+    path: PathBuf,
+    is_dir: bool,
+    name: FileName,
+    // justification for maybe(date) and maybe(size):
+    // dirs dont have filesize - maybe count num of items in dir and display that instead? even recursively? but for now, dir has filesize None
+    // also for permission errors or special folders like $recyclebin - probably wont give you
+    // any metadata about size or date modified
+    // so entries might have filedate None
+    size: Option<FileSize>,
+    date: Option<FileDate>, 
+    // size and date aren't read yet, as opposed to not available
+    loading: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Mode {
+    Filter,
+    Normal,
+    Jump,
+    EditHistory,
+    CommandPalette,
+    OpenWith,
+    Pager,
+}
+
+// change state and do side effects
+#[derive(Clone, PartialEq, Debug)]
+enum Action {
+    GotoDir(PathBuf),
+    SetFilterText(String),
+    SelectEntryUnderCursor,
+    StartFilterMode,
+    ChangeSortOrder(EntryAttribute),
+    ReverseSort,
+    StartJumpMode,
+    SetJumpText(String),
+    JumpToDir(HistoryPath),
+    ExitJumpMode,
+    StartEditHistoryMode,
+    ExitEditHistoryMode,
+    RemoveFromHistory(HistoryPath),
+    TogglePinInHistory(HistoryPath),
+    ResetHistoryScore(HistoryPath),
+    ToggleMarkUnderCursor,
+    MarkRangeToCursor,
+    MarkAll,
+    InvertMarks,
+    ClearMarks,
+    YankSelected,
+    CutSelected,
+    Paste,
+    TrashSelected,
+    DeleteSelectedPermanently,
+    // the selection above, snapshotted when asking for confirmation,
+    // so a refresh that moves the cursor can't change what gets deleted
+    TrashPaths(Vec<PathBuf>),
+    DeletePaths(Vec<PathBuf>),
+    GotoTrash,
+    RestoreSelectedFromTrash,
+    BatchRename,
+    Undo,
+    CancelJob,
+    StartNewFilePrompt,
+    StartNewDirPrompt,
+    StartRenamePrompt,
+    CreateFile(PathBuf),
+    CreateDir(PathBuf),
+    RenamePath(PathBuf, PathBuf),
+    StartNormalMode,
+    StartCommandPaletteMode,
+    SetPaletteText(String),
+    ExitCommandPaletteMode,
+    RunPaletteCommandUnderCursor,
+    StartOpenWithMode,
+    SetOpenWithText(String),
+    ExitOpenWithMode,
+    OpenWithChoiceUnderCursor,
+    StartSubshell,
+    TogglePreview,
+    StartPager,
+    ExitPager,
+    PagerScrollDown(usize),
+    PagerScrollUp(usize),
+    PagerGotoStart,
+    PagerGotoEnd,
+    PagerToggleHex,
+    StartPagerSearchPrompt,
+    PagerSearch(String),
+    PagerSearchAgain { forward: bool },
+    RunCustomCommand(CustomCommand),
+    TryCursorMoveUp,
+    TryCursorMoveDown,
+    Noop,
+    Quit,
+}
+
+// one line of the history file
+#[derive(Debug)]
+struct HistoryRecord {
+    path: String,
+    data: HistoryData,
+}
+
+// what fmin knows about a visited dir, besides its path
+#[derive(Clone, Debug, PartialEq)]
+struct HistoryData {
+    frequency: HistoryFrequency,
+    // None for records migrated from version 1 files, which only stored frequency
+    last_visit: Option<DateTime<Local>>,
+    // the sort order last picked in the dir. only recorded for now, new dirs still open sorted by name
+    sort: Option<SortBy>,
+    // pinned dirs stay at the top of the jump list and never age out
+    pinned: bool,
+}
+
+// a row in the command palette
+#[derive(Clone)]
+struct PaletteCommand {
+    title: String,
+    // how to do the same thing without the palette; empty if there's no keybinding
+    keys: String,
+    action: Action,
+}
+
+// a shell one-liner imported from an env var like
+// FMIN_CMD_0='#unzip
+//   tar -xvf (cat $FMIN_HOME/fmin.highlighted_path)'
+// where the #comment is the title to search for in the palette
+#[derive(Clone, PartialEq, Debug)]
+struct CustomCommand {
+    env_var: String,
+    title: String,
+    command: String,
+}
+
+// what was copied or cut, until it gets pasted somewhere
+#[derive(Clone)]
+struct YankRegister {
+    paths: Vec<PathBuf>,
+    // cut entries move on paste, and can only be pasted once
+    cut: bool,
+}
+
+// eg. "permanently delete 3 entries?", then the action to run on y
+struct Confirmation {
+    prompt: String,
+    action: Action,
+}
+
+// returns what's wrong with the text, if anything
+type Validator = Box<dyn Fn(&str) -> Result<(), String>>;
+
+// a one-line text input in the footer, eg. "rename to: notes.txt".
+// reusable for anything that needs a bit of text before running an action
+struct Prompt {
+    label: String,
+    text: String,
+    // checked whenever the text changes, so problems show up before pressing Enter
+    validate: Validator,
+    // what validate said about the current text, shown in the footer
+    problem: Option<String>,
+    // turns the finished text into the action to run
+    submit: Box<dyn Fn(String) -> Action>,
+}
+
+// what to do once a program that was given the terminal exits
+enum AfterChildExits {
+    // the names of originals are in temp_path, one per line, being edited
+    BatchRename { originals: Vec<PathBuf>, temp_path: PathBuf, editor: String },
+    // a terminal program like vim opening a file
+    Opened { command: String },
+    // $SHELL, which can exit however it likes. $FMIN_SELECTED is removed after
+    Subshell { selected_paths_file: PathBuf },
+}
+
+// from threads working in the background, arriving as ProgramEvent::Message
+enum Message {
+    // a batch of sizes and dates for the cwd. finished is set on the last one
+    MetadataLoaded { loader_id: usize, loaded: Vec<LoadedMetadata>, finished: bool },
+    PreviewLoaded(PathBuf, Preview),
+    // the running job is done, and can be waited on without blocking
+    JobFinished,
+    // something inside the cwd was added, removed or changed
+    // changed is which entries, if the watcher knows, eg. with inotify but not when polling the mtime
+    CwdChanged { watcher_id: usize, changed: Option<Vec<PathBuf>> },
+    // a custom command exited, and this is what it said
+    CustomCommandFinished(String),
+    // number of files and bytes inside paths, for the confirmation prompt
+    SelectionMeasured { paths: Vec<PathBuf>, num_files: usize, num_bytes: u64 },
+}
+
+// a row in the jump list
+struct JumpCandidate {
+    path: HistoryPath,
+    score: f64,
+    pinned: bool,
+    // eg. an unplugged USB drive. still listed, in case it comes back
+    missing: bool,
+}
+
+// a change made during this session.
+// kept around so it can be replayed on top of whatever other sessions wrote in the meantime
+#[derive(Clone, Debug)]
+enum HistoryChange {
+    Visit(HistoryPath, DateTime<Local>),
+    Sort(HistoryPath, SortBy),
+    Remove(HistoryPath),
+    Pin(HistoryPath, bool),
+    Reset(HistoryPath),
+}
+
+#[derive(Debug)]
+struct FailedToReadHistory;
+#[derive(Debug)]
+struct FailedToWriteHistory;
+
+// held while the file it locks is being read + rewritten; unlocks when dropped.
+// the file is only kept open, never read
+struct FileLock(#[allow(dead_code)] File);
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum EntryAttribute {
+    Name,
+    Size,
+    Date,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct SortBy {
+    attribute: EntryAttribute,
+    ascending: bool,
+}
+
+#[derive(PartialEq, Eq, Clone)]
+struct FileName(String);
+
+#[derive(PartialEq, Clone)]
+struct FileSize(u64);
+
+#[derive(PartialEq, Clone)]
+struct FileDate(DateTime<Local>);
+
+// the list's formatted cells, by path, from the rows that were on screen last frame.
+// so scrolling by one row only formats that row, and a frame where nothing moved formats nothing
+struct RowCells {
+    // names are cut to fit this
+    name_width: usize,
+    // dates show the time if they're from this year, else the year
+    this_year: i32,
+    rows: HashMap<PathBuf, RowCell>,
+}
+
+struct RowCell {
+    // what the cells were made from, in case the entry's changed since, eg. by a refresh
+    name: FileName,
+    size: Option<FileSize>,
+    date: Option<FileDate>,
+    loading: bool,
+    name_cell: String,
+    size_cell: String,
+    date_cell: String,
+}
+
+struct ListViewData<T> {
+    items: Vec<T>, // row of Entry, row of JumpCandidate, row of Command; could be anything stringified
+                   // maybe try Vec<ListItem> where ListItem impls display() and on_enter()
+    first_viewable_index: usize,
+    cursor_index: usize,
+    max_items_visible: usize,
+    // last_viewable_index = math.min (items.length - 1) , (max_items_visible - first_index)
+}
+
+// --- associated behavior for data structures --- //
+
+impl Ord for FileName {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let a = &self.0;
+        let b = &other.0;
+        // bad: assumes .chars() will work with unicode filenames
+        // and assumes filename has at least one character
+        let a_is_dir = a.ends_with('/');
+        let b_is_dir = b.ends_with('/');
+
+        match (a_is_dir, b_is_dir) {
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            _ => b.to_string().to_lowercase().cmp(&a.to_string().to_lowercase())
+        }
+    }
+}
+
+impl PartialOrd for FileName {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Clone for Entry {
+    fn clone(&self) -> Entry {
+        Entry {
+            path: self.path.clone(),
+            is_dir: self.is_dir,
+            name: self.name.clone(),
+            size: self.size.clone(),
+            date: self.date.clone(),
+            loading: self.loading,
+        }
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for Entry {}
+
+impl Hash for Entry {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+    }
+}
+
+impl Display for FileName {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Display for FileSize {
+    // formatting options to explore:
+    //
+    // 1) two sig figs / digits, like
+    // 0 - 99 B
+    // 0.1 - 9.9, 10 - 99
+    // KB, MB, GB
+    // it has max 6 char widths
+    //   4 B
+    // 1.5 KB
+    //  27 MB
+    // 0.9 GB
+    //
+    // 2) or 3 sig figs, like
+    // 0-999 B
+    // 1.00 - 9.99
+    // 10.0 - 99.9
+    // 100 - 999
+    // KB, MB, GB
+    // it has max 7 char widths
+    //    1 B
+    //  514 KB
+    // 87.2 MB
+    // 2.31 GB
+    //
+    // 2.5) or just dont use decimals at all
+    //
+    // 3) copy how ls -lh does it
+    //
+    // 4) use standard behavior from bytes crate, like
+    // let byte = byte_unit::Byte::from_bytes(self.0.into()).get_appropriate_unit(false);
+    // "999.99 GB"  "1 B"
+    // which is max 9 chars, min 3. not sure of the formatting alogorithm tho.
+    //
+    // 5) do like windows file explorer, everything just in kb...
+    // 78,696 KB
+    // 890 KB
+    // 7 KB
+    //
+    // 6) always go with 1 decimal precision
+    // 7 max char widths
+    // 4 B
+    // 1.5 K
+    // 900.1 M
+    // 27.9 G
+    // which is good to be more consistent and therefore more readable than varying decimal places
+    //
+    // also note KB vs kb vs KiB vs Kb vs kB... just go with the all caps powers of 10 i think
+
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        const SUFFIXES : [&str; 4] = ["G", "M", "K", "B"];
+        const MAGNITUDES : [u64; 4] = [
+            u64::pow(10,9),
+            u64::pow(10,6),
+            u64::pow(10,3),
+            1
+        ];
+
+        let num_bytes = self.0;
+        match num_bytes {
+            b if b < u64::pow(10,3) => { 
+                write!(f, "{} B", num_bytes)
+            },
+            b if b >= u64::pow(10,12) => {
+                write!(f, "over 1 TB")
+            },
+            _ => {
+                let mut i = 0;
+                while MAGNITUDES[i] > num_bytes {
+                    i += 1;
+                }
+                write!(f, "{:.1} {}", num_bytes as f64 / MAGNITUDES[i] as f64, SUFFIXES[i])
+            },
+        }
+    }
+}
+
+impl Display for FileDate {
+    // options for formatting last modified date:
+    //
+    // 1) either go midnight commander style, showing time if this year else year for prior year
+    // which is 12 chars. eg. if now is july 2022, examples include
+    // Jan  1 23:59
+    // Jan  1 23:59
+    // Dec 31  2021
+    // Jun 15 12:00
+    // although it hurts to lose 24h time when new year begins (eg. starting jan 2022, dec 2021
+    // hours will be hidden)
+    //
+    // 1.5) shorten above a little bit by abbreviating year
+    // and i guess abbreviating hour too
+    // which is 10ch, like 
+    // Dec 31 '21
+    // Jan  1 +23
+    // Jun 12 +12
+    //
+    // 2) or even like imperium, disp how long since modified, max 4 char widths
+    // 8d.      8 d
+    // 5hr.     5 h
+    // 1min.    1 m
+    // 2mo.     2 M
+    // 1yr.     1 y
+    // 19d.    19 d
+    // aka
+    // 1-59 m
+    // 1 - 23 h
+    // 1 - 29? d
+    // 1 - 11 M
+    // 1 - 99? y
+    // bad tho because id like to see finer-grained differences, like 8d vs 8d12h
+    //
+    // 3) "10/10/10 10:10 PM"  "1/01/01 1:01 AM"
+    // max 17 char widths, min 15
+    // %-m/%-d/%y %-I:%M %p
+    //
+    // 4) "10/10/10" max 9 chars
+    // %-m/%-d/%y
+    
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.format(Local::now().year()))
+    }
+}
+
+impl FileDate {
+    // like to_string(), but without checking the clock, for formatting lots of dates at once
+    fn format(&self, this_year: i32) -> String {
+        let modified = self.0;
+        let format = match this_year == modified.year() {
+            // https://docs.rs/chrono/latest/chrono/format/strftime/index.html
+            true => "%b %e %k:%M",
+            false => "%b %e  %Y",
+        };
+        modified.format(format).to_string()
+    }
+}
+
+// width of attribute columns, assuming ascii chars
+// based on desired formatted output and what looks nice imo.
+// used in view functions later on
+const SIZE_COLUMN_WIDTH : usize = 7;
+const DATE_COLUMN_WIDTH : usize = 14;
+const STATUS_COLUMN_WIDTH : usize = 15;
+const KEYS_COLUMN_WIDTH : usize = 32;
+const MARGIN_WIDTH : usize = 2;
+const MARGIN : &str = "  ";
+const NUM_ROWS_OUTSIDE_LISTVIEW : usize = 6;
+
+impl Prompt {
+    fn new(
+        label: &str,
+        initial_text: &str,
+        validate: impl Fn(&str) -> Result<(), String> + 'static,
+        submit: impl Fn(String) -> Action + 'static,
+    ) -> Prompt {
+        let mut prompt = Prompt {
+            label: label.to_string(),
+            text: initial_text.to_string(),
+            validate: Box::new(validate),
+            problem: None,
+            submit: Box::new(submit),
+        };
+        prompt.revalidate();
+        prompt
+    }
+
+    fn revalidate(&mut self) {
+        self.problem = (self.validate)(&self.text).err();
+    }
+}
+
+impl RowCell {
+    fn new(entry: &Entry, name_width: usize, this_year: i32) -> RowCell {
+        let size = match &entry.size {
+            Some(size_bytes) => size_bytes.to_string(),
+            None => String::new(),
+        };
+        let date = match &entry.date {
+            Some(date_modified) => date_modified.format(this_year),
+            None if entry.loading => "loading…".to_string(),
+            None => String::new(),
+        };
+        RowCell {
+            name: entry.name.clone(),
+            size: entry.size.clone(),
+            date: entry.date.clone(),
+            loading: entry.loading,
+            name_cell: fit_to_length(&entry.name.0, name_width),
+            size_cell: fit_to_length(&pad_align_right(&size, SIZE_COLUMN_WIDTH), SIZE_COLUMN_WIDTH),
+            date_cell: fit_to_length(&date, DATE_COLUMN_WIDTH),
+        }
+    }
+
+    fn is_made_from(&self, entry: &Entry) -> bool {
+        self.name == entry.name && self.size == entry.size && self.date == entry.date && self.loading == entry.loading
+    }
+}
+
+impl<T> ListViewData<T> {
+    fn new(items: Vec<T>, max_items_visible: usize) -> Self {
+        Self {
+            items,
+            first_viewable_index: 0,
+            cursor_index: 0,
+            max_items_visible,
+        }
+    }
+    fn reset_with_items(&mut self, items: Vec<T>) {
+        self.items = items;
+        self.cursor_index = 0;
+        self.first_viewable_index = 0;
+    }
+    // fn increment_cursor(&self) -> ListViewData {
+    // return Self {
+    //  ..self,
+    //  cursor_index: new_value
+    // }
+    fn increment_cursor(&mut self) {
+        let last_viewable_index = self.max_items_visible + self.first_viewable_index - 1;
+
+        // at last index (or empty list), no movement possible
+        if self.items.is_empty() || self.cursor_index == self.items.len() - 1 {
+            // noop
+        }
+        // at bottom of list, and you can scroll down
+        else if self.cursor_index == last_viewable_index {
+            self.cursor_index += 1;
+            self.first_viewable_index += 1;
+        }
+        // in middle of list, no need to scroll yet
+        else {
+            self.cursor_index += 1;
+        }
+    }
+    fn decrement_cursor(&mut self) {
+        // at first index, no movement possible
+        if self.cursor_index == 0 {
+            // noop
+        }
+        // at top of list, when scrolling is possible
+        else if self.cursor_index == self.first_viewable_index {
+            self.cursor_index -= 1;
+            self.first_viewable_index -= 1;
+        }
+        // middle of list, no need to scroll yet
+        else {
+            self.cursor_index -= 1;
+        }
+    }
+    // to listen for cursor move up:
+    //   if cursor == 0 then no op
+    //   elif cursor == first index then first index --, and last index --, and cursor --
+    //   else cursor --
+    // and listen for cursor move down:
+    //   if cursor == list length then no op
+    //   elif cursor == last index then first index++, last index ++, and cursor ++
+    //   else cursor ++
+
+    #[allow(dead_code)]
+    fn set_max_height(&self, num_rows: usize) {
+    }
+
+    fn item_under_cursor(&self) -> Option<&T> {
+        self.items.get(self.cursor_index)
+    }
+
+    // like reset_with_items, but for when the list only changed a little,
+    // so the cursor stays put instead of jumping back to the top
+    fn replace_items(&mut self, items: Vec<T>) {
+        self.items = items;
+        self.set_cursor(self.cursor_index);
+    }
+
+    // moves the cursor anywhere, scrolling just enough to keep it in view
+    fn set_cursor(&mut self, index: usize) {
+        self.cursor_index = index.min(self.items.len().saturating_sub(1));
+        if self.cursor_index < self.first_viewable_index {
+            self.first_viewable_index = self.cursor_index;
+        }
+        if self.cursor_index >= self.first_viewable_index + self.max_items_visible {
+            self.first_viewable_index = self.cursor_index + 1 - self.max_items_visible;
+        }
+    }
+}
+
+impl Default for SortBy {
+    // dirs first, then files, each alphabetically
+    fn default() -> Self {
+        SortBy { attribute: EntryAttribute::Name, ascending: false }
+    }
+}
+
+impl SortBy {
+    // one char, for the history file
+    fn to_char(self) -> char {
+        let c = match self.attribute {
+            EntryAttribute::Name => 'n',
+            EntryAttribute::Size => 's',
+            EntryAttribute::Date => 'm',
+        };
+        match self.ascending {
+            true => c.to_ascii_uppercase(),
+            false => c,
+        }
+    }
+
+    fn from_char(c: char) -> Option<Self> {
+        let attribute = match c.to_ascii_lowercase() {
+            'n' => EntryAttribute::Name,
+            's' => EntryAttribute::Size,
+            'm' => EntryAttribute::Date,
+            _ => return None,
+        };
+        Some(SortBy { attribute, ascending: c.is_ascii_uppercase() })
+    }
+
+    pub fn compare_entries(&self, a: &Entry, b: &Entry) -> Ordering {
+        match &self.attribute {
+            EntryAttribute::Name => {
+                if a.is_dir && !b.is_dir {
+                    return Ordering::Greater;
+                }
+                if !a.is_dir && b.is_dir {
+                    return Ordering::Less;
+                }
+                b.name.to_string().to_lowercase().cmp(&a.name.to_string().to_lowercase())
+            },
+            EntryAttribute::Size => {
+                match (&a.size, &b.size) {
+                    (None, Some(b)) => Ordering::Less,
+                    (Some(a), None) => Ordering::Greater,
+                    (Some(a), Some(b)) if a.0 < b.0 => Ordering::Less, 
+                    (Some(a), Some(b)) if a.0 > b.0 => Ordering::Greater,
+                    _ => Ordering::Equal,
+                }
+            }
+            EntryAttribute::Date => {
+                match (&a.date, &b.date) {
+                    (None, Some(b)) => Ordering::Less,
+                    (Some(a), None) => Ordering::Greater,
+                    (Some(a), Some(b)) if a.0 < b.0 => Ordering::Less, 
+                    (Some(a), Some(b)) if a.0 > b.0 => Ordering::Greater,
+                    _ => Ordering::Equal,
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl std::convert::From<DirEntry> for Entry {
+    // are there permission errors that could happen here?
+    fn from(oldentry: DirEntry) -> Self {
+        let path = oldentry.path();
+        // names needn't be utf-8, and a path like / or .. has none at all
+        let mut name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_else(|| path.display().to_string());
+        let is_dir = path.is_dir();
+        let (size_bytes, date_modified) = dir_loader::read_metadata(&path, is_dir);
+        if is_dir {
+            name = format!("{}/", name);
+        }
+        Self {
+            path,
+            is_dir,
+            name: FileName(name),
+            size: size_bytes,
+            date: date_modified,
+            loading: false,
+        }
+    }
+}
+
+impl std::convert::From<std::io::Error> for FailedToReadHistory {
+    fn from(err: std::io::Error) -> Self { FailedToReadHistory }
+}
+
+impl std::convert::From<std::num::ParseIntError> for FailedToReadHistory {
+   fn from(err: std::num::ParseIntError) -> Self { FailedToReadHistory }
+}
+
+impl std::convert::From<std::io::Error> for FailedToWriteHistory {
+    fn from(err: std::io::Error) -> Self { FailedToWriteHistory }
+}
+
+
+impl Display for HistoryRecord {
+    // always writes the latest version
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        // example: "-,n,1700000000,1,my/path"
+        let pinned = match self.data.pinned {
+            true => 'p',
+            false => '-',
+        };
+        let sort = match self.data.sort {
+            Some(sort) => sort.to_char(),
+            None => '-',
+        };
+        let last_visit = match self.data.last_visit {
+            Some(date) => date.timestamp().to_string(),
+            None => "-".to_string(),
+        };
+        write!(f, "{},{},{},{},{}", pinned, sort, last_visit, self.data.frequency, self.path)
+    }
+}
+
+impl HistoryRecord {
+    fn parse(line: &str, version: usize) -> Result<Self, FailedToReadHistory> {
+        // version 1 example: "1,my/path"
+        // version 2 example: "n,1700000000,1,my/path"
+        // version 3 example: "-,n,1700000000,1,my/path"
+        let num_fields = match version {
+            1 => 2,
+            2 => 4,
+            3 => 5,
+            _ => return Err(FailedToReadHistory),
+        };
+        let mut chunks = line.splitn(num_fields, ',').collect::<Vec<&str>>();
+        if chunks.len() != num_fields { return Err(FailedToReadHistory); }
+
+        let path = chunks.pop().unwrap();
+        let frequency : HistoryFrequency = chunks.pop().unwrap().parse()?;
+        let last_visit = match chunks.pop() {
+            None | Some("-") => None,
+            Some(seconds) => Local.timestamp_opt(seconds.parse()?, 0).single(),
+        };
+        let sort = match chunks.pop() {
+            None | Some("-") => None,
+            Some(c) => Some(SortBy::from_char(c.chars().next().ok_or(FailedToReadHistory)?).ok_or(FailedToReadHistory)?),
+        };
+        let pinned = match chunks.pop() {
+            None | Some("-") => false,
+            Some("p") => true,
+            Some(_) => return Err(FailedToReadHistory),
+        };
+        if path.is_empty() { return Err(FailedToReadHistory); }
+
+        Ok(Self {
+            path: path.to_string(),
+            data: HistoryData { frequency, last_visit, sort, pinned },
+        })
+    }
+}
+
+// everything at once, for when there's no screen to show names on early, like previews.
+// the cwd loads in two steps instead, see load_cwd()
+fn read_directory_contents_into_sorted(dir: &Path, sort: SortBy) -> Vec<Entry> {
+    // TODO - measure to confirm binary heap is actually faster than simple vec
+
+    let mut name_sorted_heap = BinaryHeap::new_by(|a: &Entry, b: &Entry| sort.compare_entries(a, b) );
+    
+    match dir.read_dir() {
+        Ok(dir_entries) => {
+            for direntry in dir_entries {
+                match direntry {
+                    Ok(e) => name_sorted_heap.push(Entry::from(e)),
+                    // todo: give better msg if io err happens getting dir entry
+                    Err(err) => (),
+                };
+            }
+        },
+        // todo: give better msg if io err reading dir
+        Err(err) => (),
+    };
+
+    // same direction as sort_entries()
+    match sort.ascending {
+        true => name_sorted_heap.into_sorted_vec(),
+        false => name_sorted_heap.into_sorted_vec().into_iter().rev().collect(),
+    }
+}
+
+fn filter_entries(entries: &[Entry], filter_text: &str) -> Vec<Entry> {
+    let filter_text = filter_text.to_lowercase();
+    entries
+        .iter()
+        .filter(|entry| entry.name.0.to_lowercase().contains(&filter_text))
+        .cloned()
+        .collect()
+}
+
+fn sort_entries(entries: &[Entry], sort: SortBy) -> Vec<Entry> {
+    // let mut new_entries = entries.into_iter().collect::<Vec<&Entry>>();
+    let mut new_entries = entries.to_vec();
+    match sort.attribute {
+        // same order as compare_entries(), but lowercases each name once instead of on every comparison,
+        // which adds up to seconds in a dir with 100k+ entries
+        EntryAttribute::Name => new_entries.sort_by_cached_key(|entry| (entry.is_dir, Reverse(entry.name.0.to_lowercase()))),
+        _ => new_entries.sort_by(|a,b| sort.compare_entries(a, b)),
+    };
+    if !sort.ascending {
+        new_entries.reverse();
+    }
+    new_entries
+}
+
+type HistoryPath = String;
+type HistoryFrequency = usize;
+
+const HISTORY_HEADER : &str = "#fmin_history";
+const HISTORY_VERSION : usize = 3;
+// once the sum of all frequencies passes this, every frequency decays. same default as zoxide
+const HISTORY_MAX_AGE : usize = 10000;
+
+fn read_history_file(filename: &Path) -> Result<HashMap<HistoryPath, HistoryData>, FailedToReadHistory> {
+    // input:
+    // #fmin_history 3
+    // -,n,1700000000,123,/my/path
+    // p,-,-,45,/another/path
+    //
+    // or an older version like 2, without the pinned field,
+    // or the original headerless version 1, which is migrated on the next write:
+    // 123,/my/path
+    // 45,/another/path
+    let mut history = HashMap::<HistoryPath, HistoryData>::new();
+
+    let file = File::open(filename)?;
+
+    // line reading taken from:
+    // https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html 
+    let mut lines = BufReader::new(file).lines().peekable();
+
+    let version = match lines.peek() {
+        Some(Ok(first_line)) if first_line.starts_with(HISTORY_HEADER) => {
+            let version = match first_line[HISTORY_HEADER.len()..].trim().parse() {
+                Ok(version) => version,
+                // a garbled header shouldn't lose the whole history either. any lines
+                // that don't fit the current version are skipped below like other bad lines
+                Err(_) => {
+                    log::info!("skipping bad history header: {}", first_line);
+                    HISTORY_VERSION
+                },
+            };
+            lines.next();
+            version
+        },
+        _ => 1,
+    };
+    if version > HISTORY_VERSION {
+        // written by a newer fmin, so the records can't be understood here
+        log::info!("history file version {} is newer than {}", version, HISTORY_VERSION);
+        return Err(FailedToReadHistory);
+    }
+
+    for line in lines {
+        let line = line?;
+        if line.is_empty() { continue; }
+        // one bad line shouldn't lose the whole history, so skip it
+        match HistoryRecord::parse(&line, version) {
+            Ok(record) => { history.insert(record.path, record.data); },
+            Err(FailedToReadHistory) => log::info!("skipping bad history line: {}", line),
+        };
+    }
+    Ok(history)
+}
+
+fn write_history_file(history: &HashMap<HistoryPath, HistoryData>, filepath: &Path) -> Result<(), FailedToWriteHistory> {
+    // output:
+    // #fmin_history 3
+    // -,n,1700000000,123,/my/path
+    // p,-,-,45,/another/path
+    let line_separated_records = history
+        .iter()
+        .map( |(path, data)| 
+            HistoryRecord {
+                path: path.to_string(),
+                data: data.clone(),
+            }.to_string()
+        )
+        .collect::<Vec<String>>()
+        .join("\n");
+    let line_separated_records = format!("{} {}\n{}", HISTORY_HEADER, HISTORY_VERSION, line_separated_records);
+
+    write_file_atomically(filepath, line_separated_records.as_bytes())?;
+    Ok(())
+}
+
+// history is this session's copy, with its changes already applied, for when the file can't be reread
+fn save_history(history: &HashMap<HistoryPath, HistoryData>, changes: &[HistoryChange], filepath: &Path) -> Result<(), FailedToWriteHistory> {
+    // other fmin sessions may have written the history file since this session started,
+    // so instead of overwriting their visits, reread the file and replay this session's changes on top
+    if let Some(dir) = filepath.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let _lock = lock_file(&append_to_filename(filepath, ".lock"))?;
+
+    let mut history = match filepath.exists() {
+        true => match read_history_file(filepath) {
+            Ok(history) => history,
+            // written by a newer version of fmin. better to lose this session than clobber it
+            Err(FailedToReadHistory) if is_newer_history_file(filepath) => return Err(FailedToWriteHistory),
+            // otherwise writing nothing would lose every later session's visits too,
+            // so keep what this session read at startup plus its own changes
+            Err(FailedToReadHistory) => {
+                log::info!("couldn't reread history file, writing this session's history instead");
+                let mut history = history.clone();
+                age_history(&mut history);
+                return write_history_file(&history, filepath);
+            },
+        },
+        false => HashMap::new(),
+    };
+    for change in changes {
+        apply_history_change(&mut history, change);
+    }
+    age_history(&mut history);
+    write_history_file(&history, filepath)
+}
+
+fn is_newer_history_file(filepath: &Path) -> bool {
+    let Ok(file) = File::open(filepath) else { return false; };
+    let Some(Ok(first_line)) = BufReader::new(file).lines().next() else { return false; };
+    match first_line.strip_prefix(HISTORY_HEADER).map(|version| version.trim().parse::<usize>()) {
+        Some(Ok(version)) => version > HISTORY_VERSION,
+        _ => false,
+    }
+}
+
+fn apply_history_change(history: &mut HashMap<HistoryPath, HistoryData>, change: &HistoryChange) {
+    match change {
+        HistoryChange::Visit(path, date) => increment_history(history, path.to_string(), *date),
+        HistoryChange::Sort(path, sort) => remember_sort(history, path.to_string(), *sort),
+        HistoryChange::Remove(path) => { history.remove(path); },
+        HistoryChange::Pin(path, pinned) => {
+            if let Some(data) = history.get_mut(path) { data.pinned = *pinned; }
+        },
+        HistoryChange::Reset(path) => {
+            if let Some(data) = history.get_mut(path) {
+                data.frequency = 1;
+                data.last_visit = None;
+            }
+        },
+    }
+}
+
+fn age_history(history: &mut HashMap<HistoryPath, HistoryData>) {
+    // like zoxide, keep the total bounded by scaling everything down once it gets too big,
+    // and forget dirs that decay all the way to zero.
+    // this is the only way dirs get dropped automatically; missing dirs are not dropped,
+    // since eg. USB drives come and go
+    // https://github.com/ajeetdsouza/zoxide/wiki/Algorithm#aging
+    let total : usize = history.values().filter(|data| !data.pinned).map(|data| data.frequency).sum();
+    if total <= HISTORY_MAX_AGE { return; }
+
+    let factor = 0.9 * HISTORY_MAX_AGE as f64 / total as f64;
+    for data in history.values_mut().filter(|data| !data.pinned) {
+        data.frequency = (data.frequency as f64 * factor) as HistoryFrequency;
+    }
+    history.retain(|_, data| data.pinned || data.frequency > 0);
+}
+
+// change the history in memory, and remember the change for save_history() later
+fn change_history(m: &mut Model, change: HistoryChange) {
+    apply_history_change(&mut m.history, &change);
+    m.history_changes.push(change);
+}
+
+fn increment_history(history: &mut HashMap<HistoryPath, HistoryData>, path: HistoryPath, date: DateTime<Local>) {
+    history
+        .entry(path)
+        .and_modify( |data| {
+            data.frequency += 1;
+            data.last_visit = Some(match data.last_visit {
+                Some(last_visit) if last_visit > date => last_visit,
+                _ => date,
+            });
+        })
+        .or_insert(HistoryData { frequency: 1, last_visit: Some(date), sort: None, pinned: false });
+}
+
+fn remember_sort(history: &mut HashMap<HistoryPath, HistoryData>, path: HistoryPath, sort: SortBy) {
+    if let Some(data) = history.get_mut(&path) {
+        data.sort = Some(sort);
+    }
+}
+
+fn frecency(data: &HistoryData, now: DateTime<Local>) -> f64 {
+    // same buckets as zoxide: https://github.com/ajeetdsouza/zoxide/wiki/Algorithm#frecency
+    // dirs without a known last visit are treated as visited long ago
+    let frequency = data.frequency as f64;
+    match data.last_visit {
+        Some(last_visit) => {
+            let elapsed = now.signed_duration_since(last_visit);
+            if elapsed < chrono::Duration::hours(1) { frequency * 4.0 }
+            else if elapsed < chrono::Duration::days(1) { frequency * 2.0 }
+            else if elapsed < chrono::Duration::weeks(1) { frequency / 2.0 }
+            else { frequency / 4.0 }
+        },
+        None => frequency / 4.0,
+    }
+}
+
+// the jump list for the current mode and jump text.
+// jumping to the cwd would do nothing, so it's only listed for editing
+fn rank_jump_list(m: &Model) -> Vec<JumpCandidate> {
+    let exclude = match m.mode {
+        Mode::EditHistory => None,
+        _ => Some(m.cwd.as_path()),
+    };
+    rank_history(&m.history, &m.missing_dirs, &m.jump_text, exclude)
+}
+
+// for when the list gains or loses the cwd, so the cursor stays on the same dir
+fn rerank_jump_list_keeping_cursor(m: &mut Model) {
+    let path_under_cursor = m.jump_list.item_under_cursor().map(|candidate| candidate.path.clone());
+    let candidates = rank_jump_list(m);
+    let cursor_index = candidates
+        .iter()
+        .position(|candidate| Some(&candidate.path) == path_under_cursor.as_ref())
+        .unwrap_or(0);
+    m.jump_list.replace_items(candidates);
+    m.jump_list.set_cursor(cursor_index);
+}
+
+fn rank_history(
+    history: &HashMap<HistoryPath, HistoryData>,
+    missing_dirs: &HashSet<HistoryPath>,
+    query: &str,
+    exclude: Option<&Path>,
+) -> Vec<JumpCandidate> {
+    // like zoxide, every space-separated word in the query has to appear in the path, in order.
+    // plain substring matching, no fuzzy matching (yet)
+    let now = Local::now();
+    let exclude = exclude.map(|path| path.display().to_string());
+    let query = query.to_lowercase();
+    let words = query.split_whitespace().collect::<Vec<&str>>();
+
+    let mut candidates = history
+        .iter()
+        .filter(|(path, _)| Some(*path) != exclude.as_ref())
+        .filter(|(path, _)| {
+            let path = path.to_lowercase();
+            let mut rest = path.as_str();
+            words.iter().all(|word| match rest.find(word) {
+                Some(i) => {
+                    rest = &rest[i + word.len()..];
+                    true
+                },
+                None => false,
+            })
+        })
+        .map(|(path, data)| JumpCandidate {
+            path: path.to_string(),
+            score: frecency(data, now),
+            pinned: data.pinned,
+            missing: missing_dirs.contains(path),
+        })
+        .collect::<Vec<JumpCandidate>>();
+
+    // pinned first, then highest score,
+    // then alphabetical so equal scores don't shuffle around between keypresses
+    candidates.sort_by(|a, b| {
+        b.pinned.cmp(&a.pinned)
+            .then_with(|| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal))
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    candidates
+}
+
+fn find_missing_dirs(history: &HashMap<HistoryPath, HistoryData>) -> HashSet<HistoryPath> {
+    history
+        .keys()
+        .filter(|path| !Path::new(path).is_dir())
+        .cloned()
+        .collect()
+}
+
+// --- file helpers --- //
+
+fn append_to_filename(path: &Path, suffix: &str) -> PathBuf {
+    let mut filename = path.file_name().unwrap_or_default().to_os_string();
+    filename.push(suffix);
+    path.with_file_name(filename)
+}
+
+fn write_file_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    // write everything to a temp file next to the real one, then swap it in with one rename,
+    // so a crash or a full disk never leaves a half-written file behind
+    let temp_path = append_to_filename(path, &format!(".tmp{}", std::process::id()));
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn lock_file(path: &Path) -> std::io::Result<FileLock> {
+    // advisory lock, so only cooperating fmin sessions wait on each other.
+    // uses a separate lockfile since the locked file itself gets replaced by rename
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(FileLock(file))
+}
+
+// --- custom commands --- //
+
+const CUSTOM_COMMAND_PREFIX : &str = "FMIN_CMD_";
+
+fn read_custom_commands() -> Vec<CustomCommand> {
+    let mut env_vars = std::env::vars()
+        .filter(|(key, _)| key.starts_with(CUSTOM_COMMAND_PREFIX))
+        .collect::<Vec<(String, String)>>();
+    env_vars.sort();
+
+    env_vars
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let value = value.trim();
+            // '#title \
+            //   command'
+            // the trailing backslash is optional, and only there to look nice in shell scripts
+            let (title, command) = match value.strip_prefix('#') {
+                Some(rest) => match rest.split_once('\n') {
+                    Some((title, command)) => (title.trim().trim_end_matches('\\').trim(), command.trim()),
+                    // everything after the # would be a comment to the shell too, so there's nothing to run
+                    None => {
+                        log::info!("skipping {}: only a #title, with no command on the lines after it", key);
+                        return None;
+                    },
+                },
+                // no title, so search by the command itself
+                None => (value.lines().next()?, value),
+            };
+            if command.is_empty() {
+                log::info!("skipping {}: no command to run", key);
+                return None;
+            }
+            Some(CustomCommand { env_var: key, title: title.to_string(), command: command.to_string() })
+        })
+        .collect()
+}
+
+// files for custom commands (and anything else) to read, eg. `cat $FMIN_HOME/fmin.highlighted_path`
+fn write_fmin_files(m: &Model) -> std::io::Result<()> {
+    std::fs::create_dir_all(&m.data_dir)?;
+    let highlighted_path = match m.list_view.item_under_cursor() {
+        Some(entry) => entry.path.display().to_string(),
+        None => String::new(),
+    };
+    std::fs::write(m.data_dir.join("fmin.cwd"), m.cwd.display().to_string())?;
+    std::fs::write(m.data_dir.join("fmin.highlighted_path"), highlighted_path)?;
+    std::fs::write(m.data_dir.join("fmin.selected_paths"), selected_paths_text(m))?;
+    Ok(())
+}
+
+// the selected paths, one per line, for $FMIN_SELECTED. returns the file, which the caller removes when done.
+// a new one every time, eg. fmin.selected_paths_1234_0, so other fmins and other commands can't overwrite it
+// the way they can overwrite the shared $FMIN_HOME/fmin.selected_paths
+fn write_selected_paths(m: &Model) -> std::io::Result<PathBuf> {
+    static NEXT_ID : std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    let id = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let filepath = m.data_dir.join(format!("fmin.selected_paths_{}_{}", std::process::id(), id));
+    std::fs::write(&filepath, selected_paths_text(m))?;
+    Ok(filepath)
+}
+
+fn selected_paths_text(m: &Model) -> String {
+    selected_paths(m)
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// returns a message for the status line
+// runs on another thread, so a slow command doesn't freeze fmin.
+// what it prints comes back as Message::CustomCommandFinished
+fn run_custom_command(m: &mut Model, command: &CustomCommand) {
+    let selected_paths_file = match write_fmin_files(m).and_then(|()| write_selected_paths(m)) {
+        Ok(filepath) => filepath,
+        Err(err) => {
+            m.status_message = format!("couldn't write files for {}: {}", command.title, err);
+            return;
+        },
+    };
+    // use the user's shell, since the one-liners are probably written for it (eg. fish's `(cat file)`)
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+    let mut child = std::process::Command::new(shell);
+    child
+        .arg("-c")
+        .arg(&command.command)
+        .current_dir(&m.cwd)
+        // in case fmin fell back to $XDG_DATA_HOME or $HOME/.fmin/
+        .env("FMIN_HOME", &m.data_dir)
+        .env("FMIN_SELECTED", &selected_paths_file)
+        // don't let the command read keypresses meant for fmin, or draw over the tui
+        .stdin(std::process::Stdio::null());
+    m.status_message = format!("running {}...", command.title);
+
+    let title = command.title.clone();
+    let messages = m.messages.clone();
+    std::thread::spawn(move || {
+        let said = match child.output() {
+            Ok(output) if output.status.success() => one_line(&String::from_utf8_lossy(&output.stdout)),
+            Ok(output) => format!("{} failed ({}): {}", title, output.status, one_line(&String::from_utf8_lossy(&output.stderr))),
+            Err(err) => format!("couldn't run {}: {}", title, err),
+        };
+        let _ = std::fs::remove_file(&selected_paths_file);
+        let _ = messages.send(Message::CustomCommandFinished(said));
+    });
+}
+
+// a shell in the cwd, for anything fmin can't do itself. fmin comes back when it exits.
+// a child can't change fmin's cwd or env, so this goes the other way and hands them down:
+// FMIN_CWD, FMIN_SELECTED (a file with the selected paths, one per line),
+// and FMIN_LEVEL, which counts nested shells, eg. for a prompt that says you're inside fmin
+fn start_subshell(m: &mut Model) -> UpdateResult {
+    let selected_paths_file = match write_fmin_files(m).and_then(|()| write_selected_paths(m)) {
+        Ok(filepath) => filepath,
+        Err(err) => {
+            m.status_message = format!("couldn't write files for the shell: {}", err);
+            return UpdateResult::Continue;
+        },
+    };
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+    let level = std::env::var("FMIN_LEVEL").ok().and_then(|level| level.parse::<usize>().ok()).unwrap_or(0) + 1;
+    let mut command = std::process::Command::new(shell);
+    command
+        .current_dir(&m.cwd)
+        .env("FMIN_HOME", &m.data_dir)
+        .env("FMIN_CWD", &m.cwd)
+        .env("FMIN_SELECTED", &selected_paths_file)
+        .env("FMIN_LEVEL", level.to_string());
+    m.after_child_exits = Some(AfterChildExits::Subshell { selected_paths_file });
+    UpdateResult::RunInTerminal(command)
+}
+
+fn one_line(text: &str) -> String {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>()
+        .join("  ")
+}
+
+// --- UPDATES AND APP LOGIC --- //
+
+fn main() {
+    let program_result = Program {init, view, update}.run();
+    match program_result {
+        Ok(mut model) => {
+            // quitting in the middle of a copy shouldn't leave half a file behind,
+            // and whatever did finish should still be undoable
+            if let Some(job) = &model.running_job {
+                job.cancel();
+                finish_job(&mut model);
+            }
+            if let Err(err) = write!(std::io::stdout(), "{}", model.cwd.display()) {
+                let _ = write!(std::io::stderr(), "Error: can't print the cwd: {}", err);
+            }
+            if save_history(&model.history, &model.history_changes, &model.history_filepath).is_err() {
+                let _ = write!(std::io::stderr(), "Error: couldn't save history to {}", model.history_filepath.display());
+            }
+            // maybe should save_history on every cwd update?
+        },
+        Err(msg) => {
+            let _ = write!(std::io::stderr(), "Error: {}", msg);
+        }
+        // TODO - on err, still write cwd to stdout so parent script won't fail
+    };
+}
+
+fn init(messages: Sender<Message>) -> Result<Model, String> {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Info);
+    }
+
+    log::info!("---\nnew session");
+
+    let cwd = std::env::current_dir().unwrap();
+    // let (cols, rows) = terminal::size()?;
+    let (cols, rows) = match terminal::size() {
+        Ok((cols, rows)) => (usize::from(cols), usize::from(rows)),
+        Err(_) => return Err("can't read terminal size".to_string()),
+    };
+
+    // Thoughts on dotfiles, env vars, and related conventions:
+    //
+    // I personally don't like the XDG basedir spec (https://specifications.freedesktop.org/basedir-spec/basedir-spec-latest.html)
+    //
+    // 1) the XDG spec defines several directories, including:
+    //    $XDG_DATA_HOME, XDG_CONFIG_HOME, STATE_HOME
+    //    According to the spec, fmin should use most of those dirs
+    //    (write selected entries to STATE_HOME/.fmin_selected_filepaths,
+    //     keep history in DATA_HOME/.fmin_history, 
+    //     config file at CONFIG_HOME/.fminrc,
+    //     and use CACHE_HOME if I ever get that serious)
+    //    Too many directories for a tiny tool like fmin, imo.
+    //    Twould be like having `ls` vomit four files all over your system.
+    //    Not to mention my personal preference for portable, self-contained app directories.
+    //    But that's just me.
+    //
+    // 2) the XDG spec also defines fallbacks for each env var, eg. 
+    //    "if $XDG_CONFIG_HOME is not set, a default equal to $HOME/.config should be used."
+    //    Which means the spec defines what to do if you don't follow the spec...
+    //    Or in other words, you can't fully support the XDG spec and non-XDG fallbacks 
+    //      at the same time.
+    //    That's a less-important feature of the spec, tho. Easy to ignore.
+    //
+    // 3) Some big old command-line tools have publicly stated they will never 
+    //    break compatibility to support XDG; take $HOME/.bashrc, for example.
+    //    Other tools half-heartedly support the XDG spec, 
+    //      and just dump everything to $XDG_CONFIG_HOME if it's set,
+    //        and hope that's enough to satisfy XDG-enjoyers.
+    //    Still other tools don't even bother with the XDG spec.
+    //    Very few implement the spec 100%.
+    //
+    // I think users mainly care about keeping $HOME clean
+    // and keeping config files in one place / easy to backup.
+    //
+    // But supporting the XDG spec is only a few extra LOC. So I might as well do it.
+    // For now, that means putting .fmin_history in $XDG_DATA_HOME if it's set.
+    //
+    // - fmin provides an alternative data home var like $FMIN_HOME,
+    //   meant for the users that want neither XDG nor naked $HOME.
+    //   I'm not sure if every tool should have a custom env var home,
+    //   but it feels reasonable to me for now.
+    //   Everything goes in one folder, which is at worst easy to understand and control
+    //
+    // - if those env vars are unset, then do the typical $HOME/.dotfile behavior,
+    //   because everyone is used to that,
+    //   and picky people should have developed workarounds by now.
+    //
+    // Ultimately, there will always be tools with dotfiles in $HOME,
+    // and there will always be tools half-heartedly implementing the XDG spec,
+    // whether or not anyone likes it, and whether or not it's a good spec.
+    //
+    // So I think the best behavior is educating users,
+    // embracing long-running conventions instead of breaking backwards-compatiblity,
+    // giving users an escape hatch for customization and control over any dotfile
+    //   (see symlinks,
+    //    and symlink management tools like gnu stow,
+    //    and other config-mangement options like
+    //    leaving ~/ for config while using another dir like ~/mycleanhome/ for personal use,
+    //    or perhaps using cli options --config if possible)
+    // and minimizing the amount of config needed in the first place.
+
+    let data_dir = match(
+        std::env::var("FMIN_HOME"),
+        std::env::var("XDG_DATA_HOME"),
+        std::env::var("HOME"), // TODO - replace with windows env var for home? %USERHOME% or whatver?
+    ) {
+        ( Ok(fmin_home), _, _) => PathBuf::from(fmin_home),
+        ( Err(_), Ok(xdg_config_home), _ ) => PathBuf::from(xdg_config_home),
+        ( Err(_), Err(_), Ok(home) ) => PathBuf::from(home).join(".fmin/"),
+        _ => return Err("need to set directory: either $FMIN_HOME, $XDG_DATA_HOME, or $HOME".to_string()),
+    };
+
+    Ok(new_model(cwd, cols, rows, data_dir, messages))
+}
+
+// everything init() does once it knows where it is, without touching the terminal
+fn new_model(cwd: PathBuf, cols: usize, rows: usize, data_dir: PathBuf, messages: Sender<Message>) -> Model {
+    const HISTORY_FILENAME : &str = ".fmin_history";
+    let history_filepath = data_dir.join(HISTORY_FILENAME);
+    let mut history = read_history_file(&history_filepath).unwrap_or_default();
+    // or later, in Action::GotoMode:
+    // let history = match m.history {
+    //     NotInitialized => read_history_file()
+    //     Loaded(records) => 
+    //     Failed => ...
+    // }
+    log::info!("{:?}", history);
+
+    let history_changes = vec![HistoryChange::Visit(cwd.display().to_string(), Local::now())];
+    apply_history_change(&mut history, &history_changes[0]);
+
+    let sort = SortBy::default();
+    let list_view = ListViewData::new(Vec::new(), rows - NUM_ROWS_OUTSIDE_LISTVIEW);
+    let jump_list = ListViewData::new(Vec::new(), rows - NUM_ROWS_OUTSIDE_LISTVIEW);
+    let palette_list = ListViewData::new(Vec::new(), rows - NUM_ROWS_OUTSIDE_LISTVIEW);
+    let open_with_list = ListViewData::new(Vec::new(), rows - NUM_ROWS_OUTSIDE_LISTVIEW);
+    let watcher = Watcher::start(&cwd, messages.clone());
+    let config = config::read_config(&data_dir.join(".fminrc"));
+ 
+    let mut model = Model {
+        cwd,
+        cwd_sort: sort,
+        sorted_entries: Vec::new(),
+        marked_paths: HashSet::new(),
+        mark_anchor: None,
+        metadata_loader: None,
+        dir_cache: DirCache::new(config.dir_cache_size),
+        cwd_modified: None,
+        messages: messages.clone(),
+        watcher,
+        row_cells: RefCell::new(RowCells { name_width: 0, this_year: 0, rows: HashMap::new() }),
+        recently_changed: HashMap::new(),
+        entries_before_refresh: None,
+        filter_text: "".to_string(),
+        mode: Mode::Filter,
+        cols,
+        rows,
+        list_view,
+        history_filepath,
+        history,
+        history_changes,
+        jump_text: "".to_string(),
+        jump_list,
+        missing_dirs: HashSet::new(),
+        palette_origin: Mode::Filter,
+        palette_text: "".to_string(),
+        palette_list,
+        custom_commands: read_custom_commands(),
+        open_with_origin: Mode::Filter,
+        open_with_path: PathBuf::new(),
+        open_with_text: "".to_string(),
+        open_with_choices: Vec::new(),
+        open_with_list,
+        open_with_filepath: data_dir.join(".fmin_open_with"),
+        journal_filepath: data_dir.join(".fmin_journal"),
+        config,
+        data_dir,
+        status_message: "".to_string(),
+        yank_register: None,
+        pending_confirmation: None,
+        prompt: None,
+        running_job: None,
+        after_child_exits: None,
+        preview_visible: false,
+        previewer: Previewer::start(messages),
+        previews: HashMap::new(),
+        preview_requested: None,
+        pager: None,
+        pager_origin: Mode::Filter,
+    };
+    load_cwd(&mut model, false);
+    model.list_view.reset_with_items(model.sorted_entries.clone());
+    model
+}
+
+fn update(m: &mut Model, event: ProgramEvent<Message>) -> UpdateResult {
+    let result = update_model(m, event);
+    // after anything that could've moved the cursor, so the preview keeps up
+    request_preview(m);
+    result
+}
+
+fn update_model(m: &mut Model, event: ProgramEvent<Message>) -> UpdateResult {
+    let terminal_event = match event {
+        ProgramEvent::Terminal(terminal_event) => terminal_event,
+        ProgramEvent::Tick => return tick(m),
+        ProgramEvent::Resumed(status) => return resume(m, status),
+        ProgramEvent::Message(message) => return receive_message(m, message),
+    };
+    // exit early if ctrl+c, no matter what
+    // returning None means to quit the program
+    // TODO - have a better return type than None/Some(())
+
+    if let Event::Key(_) = terminal_event {
+        m.status_message.clear();
+    }
+
+    match terminal_event {
+        Event::Key(keyevent) if
+            keyevent.modifiers == KeyModifiers::CONTROL &&
+            keyevent.code == KeyCode::Char('c') =>
+        {
+            return UpdateResult::Finish;
+        },
+        Event::Key(keyevent) if
+            keyevent.modifiers == KeyModifiers::CONTROL &&
+            keyevent.code == KeyCode::Char('z') =>
+        {
+            return UpdateResult::Suspend;
+        },
+        // ctrl+p opens the palette from any mode, and moves up once inside it
+        Event::Key(keyevent) if
+            keyevent.modifiers == KeyModifiers::CONTROL &&
+            keyevent.code == KeyCode::Char('p') &&
+            m.mode != Mode::CommandPalette &&
+            m.mode != Mode::OpenWith &&
+            m.pending_confirmation.is_none() &&
+            m.prompt.is_none() =>
+        {
+            return update_with_action(m, Action::StartCommandPaletteMode);
+        },
+        Event::Resize(cols, rows) => {
+            m.cols = usize::from(cols);
+            m.rows = usize::from(rows);
+            m.list_view.max_items_visible = m.rows - NUM_ROWS_OUTSIDE_LISTVIEW;
+            m.jump_list.max_items_visible = m.rows - NUM_ROWS_OUTSIDE_LISTVIEW;
+            m.palette_list.max_items_visible = m.rows - NUM_ROWS_OUTSIDE_LISTVIEW;
+            m.open_with_list.max_items_visible = m.rows - NUM_ROWS_OUTSIDE_LISTVIEW;
+            if let Some(pager) = &mut m.pager {
+                pager.set_rows(m.rows - NUM_ROWS_OUTSIDE_LISTVIEW);
+            }
+        },
+        _ => ()
+    };
+    // a pending confirmation takes every keypress until it's answered
+    if let (Some(_), Event::Key(keyevent)) = (&m.pending_confirmation, terminal_event) {
+        return answer_confirmation(m, keyevent.code);
+    }
+    // same for a prompt, until it's submitted or cancelled
+    if let (Some(_), Event::Key(keyevent)) = (&m.prompt, terminal_event) {
+        return answer_prompt(m, keyevent.code);
+    }
+    let action = key_action(m, terminal_event);
+    update_with_action(m, action)
+}
+
+// respond to crossterm event and output an action.
+// the palette lists these same keys, which the tests check
+fn key_action(m: &Model, terminal_event: Event) -> Action {
+    match m.mode {
+        Mode::Normal => {
+            match terminal_event {
+                Event::Key(keyevent) => {
+                    match keyevent.code {
+                        KeyCode::Char('/') => Action::StartFilterMode,
+                        KeyCode::Char('>') => Action::StartJumpMode, // maybe @ would work?
+                        KeyCode::Char(':') => Action::StartCommandPaletteMode,
+                        KeyCode::Backspace => {
+                            match m.cwd.parent() {
+                                Some(path) => Action::GotoDir(path.to_owned()),
+                                None => Action::Noop,
+                            }
+                        },
+                        KeyCode::Char('n') => {
+                            // Action::ChangeSortOrder(SortBy{ 
+                            //     attribute: EntryAttribute::Name,
+                            //     ascending: match m.cwd_sort.attribute == EntryAttribute::Name {
+                            //         EntryAttribute::Name => !m.cwd_sort.ascending,
+                            //         _ => true,
+                            //     }
+                            // }),
+                            match m.cwd_sort.attribute {
+                                EntryAttribute::Name => Action::ReverseSort,
+                                _ => Action::ChangeSortOrder(EntryAttribute::Name),
+                            }
+                        },
+                        KeyCode::Char('s') => {
+                            match m.cwd_sort.attribute {
+                                EntryAttribute::Size => Action::ReverseSort,
+                                _ => Action::ChangeSortOrder(EntryAttribute::Size),
+                            }
+                        },
+                        KeyCode::Char('m') => {
+                            match m.cwd_sort.attribute {
+                                EntryAttribute::Date => Action::ReverseSort,
+                                _ => Action::ChangeSortOrder(EntryAttribute::Date),
+                            }
+                        },
+                        KeyCode::Char('k') | KeyCode::Up => Action::TryCursorMoveUp,
+                        KeyCode::Char('j') | KeyCode::Down => Action::TryCursorMoveDown,
+                        KeyCode::Enter => Action::SelectEntryUnderCursor,
+                        KeyCode::Char('w') => Action::StartOpenWithMode,
+                        KeyCode::Char('L') => Action::StartPager,
+                        KeyCode::Char('t') => Action::StartSubshell,
+                        KeyCode::Char(' ') => Action::ToggleMarkUnderCursor,
+                        KeyCode::Char('v') => Action::MarkRangeToCursor,
+                        KeyCode::Char('a') => Action::MarkAll,
+                        KeyCode::Char('i') => Action::InvertMarks,
+                        KeyCode::Esc => Action::ClearMarks,
+                        KeyCode::Char('y') => Action::YankSelected,
+                        KeyCode::Char('x') => Action::CutSelected,
+                        KeyCode::Char('p') => Action::Paste,
+                        KeyCode::Char('d') | KeyCode::Delete => Action::TrashSelected,
+                        KeyCode::Char('D') => Action::DeleteSelectedPermanently,
+                        KeyCode::Char('R') => Action::BatchRename,
+                        KeyCode::Char('u') => Action::Undo,
+                        KeyCode::Char('c') => Action::CancelJob,
+                        KeyCode::Char('r') | KeyCode::F(2) => Action::StartRenamePrompt,
+                        KeyCode::F(3) => Action::TogglePreview,
+                        KeyCode::Char('q') => Action::Quit,
+                        _ => Action::Noop,
+                    }
+                },
+                _ => Action::Noop,
+            }
+        },
+        Mode::Filter => {
+            match terminal_event {
+                Event::Key(keyevent) => {
+                    match keyevent.modifiers {
+                        KeyModifiers::SHIFT => match keyevent.code {
+                            KeyCode::Char('K') => Action::TryCursorMoveUp,
+                            KeyCode::Char('J') => Action::TryCursorMoveDown,
+                            KeyCode::Char('N') => match m.cwd_sort.attribute {
+                                EntryAttribute::Name => Action::ReverseSort,
+                                _ => Action::ChangeSortOrder(EntryAttribute::Name),
+                            },
+                            KeyCode::Char('S') => match m.cwd_sort.attribute {
+                                EntryAttribute::Size => Action::ReverseSort,
+                                _ => Action::ChangeSortOrder(EntryAttribute::Size),
+                            },
+                            KeyCode::Char('M') => match m.cwd_sort.attribute {
+                                EntryAttribute::Date => Action::ReverseSort,
+                                _ => Action::ChangeSortOrder(EntryAttribute::Date),
+                            },
+                            KeyCode::Char('O') => Action::StartJumpMode,
+                            KeyCode::Char('P') => Action::StartCommandPaletteMode,
+                            KeyCode::Char('W') => Action::StartOpenWithMode,
+                            KeyCode::Char('L') => Action::StartPager,
+                            KeyCode::Char('T') => Action::StartSubshell,
+                            KeyCode::Char('V') => Action::MarkRangeToCursor,
+                            KeyCode::Char('A') => Action::MarkAll,
+                            KeyCode::Char('I') => Action::InvertMarks,
+                            KeyCode::Char('Y') => Action::YankSelected,
+                            KeyCode::Char('X') => Action::CutSelected,
+                            KeyCode::Delete => Action::DeleteSelectedPermanently,
+                            KeyCode::Char('R') => Action::BatchRename,
+                            KeyCode::Char('U') => Action::Undo,
+                            KeyCode::Char('C') => Action::CancelJob,
+                            KeyCode::Char('Q') => Action::Quit,
+                            _ => Action::Noop,
+                        },
+                        // shift+p already opens the palette
+                        KeyModifiers::CONTROL => match keyevent.code {
+                            KeyCode::Char('v') => Action::Paste,
+                            _ => Action::Noop,
+                        },
+                        _ => match keyevent.code {
+                            // todo: listen for end-of-input ctrl+d, arrow keys left and right, paste
+                            // ideally a readline lib is used to listen for input here, but
+                            // - dont need history or multiline editing, and probably not vim/emacs shortcuts
+                            // - not sure how to include static .so external dependency
+                            // so probably not worth including that extra dependency
+                            KeyCode::Esc => Action::SetFilterText("".to_string()),
+                            // space is fair game for filenames, so tab marks entries while filtering
+                            KeyCode::Tab => Action::ToggleMarkUnderCursor,
+                            KeyCode::Char(c) => {
+                                Action::SetFilterText(format!("{}{}", m.filter_text, c))
+                            },
+                            KeyCode::Backspace => {
+                                match m.filter_text.is_empty() {
+                                    // if filter text input is empty, backspace will nav back as if in
+                                    // normal mode.
+                                    // this reduces keypresses when navigating since normal mode is not
+                                    // default.
+                                    true => match m.cwd.parent() {
+                                        Some(path) => Action::GotoDir(path.to_owned()),
+                                        None => Action::SetFilterText("".to_string()), // clear and go normal mode
+                                    },
+                                    // remove last char. warning: doesnt account for unicode.
+                                    // but this quick and dirty solution is ok for now.
+                                    // and most filepath inputs will only have ascii anyways.
+                                    false => {
+                                        let mut chars = m.filter_text.chars();
+                                        chars.next_back();
+                                        let all_chars_but_last = chars.as_str().to_string();
+                                        Action::SetFilterText(all_chars_but_last) // potentially clear and go normal mode
+                                    },
+                                }
+                            },
+                            KeyCode::Up => {
+                                Action::TryCursorMoveUp
+                            },
+                            KeyCode::Down => {
+                                Action::TryCursorMoveDown
+                            },
+                            KeyCode::Enter => Action::SelectEntryUnderCursor,
+                            KeyCode::Delete => Action::TrashSelected,
+                            KeyCode::F(2) => Action::StartRenamePrompt,
+                            KeyCode::F(3) => Action::TogglePreview,
+                            _ => Action::Noop,
+                        }
+                    }
+                },
+                _ => Action::Noop,
+            }
+        },
+        Mode::Jump => {
+            match terminal_event {
+                Event::Key(keyevent) => {
+                    match keyevent.modifiers {
+                        // paths can have capital letters, so type them like any other char
+                        KeyModifiers::SHIFT => match keyevent.code {
+                            KeyCode::Char(c) => Action::SetJumpText(format!("{}{}", m.jump_text, c)),
+                            _ => Action::Noop,
+                        },
+                        KeyModifiers::CONTROL => match keyevent.code {
+                            KeyCode::Char('k') => Action::TryCursorMoveUp,
+                            KeyCode::Char('j') => Action::TryCursorMoveDown,
+                            KeyCode::Char('e') => Action::StartEditHistoryMode,
+                            _ => Action::Noop,
+                        },
+                        _ => match keyevent.code {
+                            KeyCode::Esc => Action::ExitJumpMode,
+                            KeyCode::Char(c) => Action::SetJumpText(format!("{}{}", m.jump_text, c)),
+                            KeyCode::Backspace => {
+                                let mut chars = m.jump_text.chars();
+                                chars.next_back();
+                                Action::SetJumpText(chars.as_str().to_string())
+                            },
+                            KeyCode::Up => Action::TryCursorMoveUp,
+                            KeyCode::Down => Action::TryCursorMoveDown,
+                            KeyCode::Enter => match m.jump_list.item_under_cursor() {
+                                Some(candidate) => Action::JumpToDir(candidate.path.clone()),
+                                None => Action::Noop,
+                            },
+                            _ => Action::Noop,
+                        }
+                    }
+                },
+                _ => Action::Noop,
+            }
+        },
+        Mode::EditHistory => {
+            let path_under_cursor = m.jump_list.item_under_cursor().map(|candidate| candidate.path.clone());
+            match terminal_event {
+                Event::Key(keyevent) => match (keyevent.code, path_under_cursor) {
+                    (KeyCode::Char('k') | KeyCode::Up, _) => Action::TryCursorMoveUp,
+                    (KeyCode::Char('j') | KeyCode::Down, _) => Action::TryCursorMoveDown,
+                    (KeyCode::Char('d') | KeyCode::Delete, Some(path)) => Action::RemoveFromHistory(path),
+                    (KeyCode::Char('p'), Some(path)) => Action::TogglePinInHistory(path),
+                    (KeyCode::Char('r'), Some(path)) => Action::ResetHistoryScore(path),
+                    (KeyCode::Esc | KeyCode::Char('q'), _) => Action::ExitEditHistoryMode,
+                    _ => Action::Noop,
+                },
+                _ => Action::Noop,
+            }
+        },
+        Mode::CommandPalette => {
+            match terminal_event {
+                Event::Key(keyevent) => {
+                    match keyevent.modifiers {
+                        KeyModifiers::CONTROL => match keyevent.code {
+                            KeyCode::Char('p') => Action::TryCursorMoveUp,
+                            KeyCode::Char('n') => Action::TryCursorMoveDown,
+                            _ => Action::Noop,
+                        },
+                        _ => match keyevent.code {
+                            KeyCode::Esc => Action::ExitCommandPaletteMode,
+                            KeyCode::Char(c) => Action::SetPaletteText(format!("{}{}", m.palette_text, c)),
+                            KeyCode::Backspace => {
+                                let mut chars = m.palette_text.chars();
+                                chars.next_back();
+                                Action::SetPaletteText(chars.as_str().to_string())
+                            },
+                            KeyCode::Up => Action::TryCursorMoveUp,
+                            KeyCode::Down => Action::TryCursorMoveDown,
+                            KeyCode::Enter => Action::RunPaletteCommandUnderCursor,
+                            _ => Action::Noop,
+                        }
+                    }
+                },
+                _ => Action::Noop,
+            }
+        },
+        Mode::Pager => {
+            let page = m.rows - NUM_ROWS_OUTSIDE_LISTVIEW;
+            match terminal_event {
+                Event::Key(keyevent) => match keyevent.code {
+                    KeyCode::Char('j') | KeyCode::Down | KeyCode::Enter => Action::PagerScrollDown(1),
+                    KeyCode::Char('k') | KeyCode::Up => Action::PagerScrollUp(1),
+                    KeyCode::Char(' ') | KeyCode::Char('f') | KeyCode::PageDown => Action::PagerScrollDown(page),
+                    KeyCode::Char('b') | KeyCode::PageUp => Action::PagerScrollUp(page),
+                    KeyCode::Char('d') => Action::PagerScrollDown(page / 2),
+                    KeyCode::Char('u') => Action::PagerScrollUp(page / 2),
+                    KeyCode::Char('g') | KeyCode::Home => Action::PagerGotoStart,
+                    KeyCode::Char('G') | KeyCode::End => Action::PagerGotoEnd,
+                    KeyCode::Char('/') => Action::StartPagerSearchPrompt,
+                    KeyCode::Char('n') => Action::PagerSearchAgain { forward: true },
+                    KeyCode::Char('N') => Action::PagerSearchAgain { forward: false },
+                    KeyCode::Char('x') => Action::PagerToggleHex,
+                    KeyCode::Char('q') | KeyCode::Esc => Action::ExitPager,
+                    _ => Action::Noop,
+                },
+                _ => Action::Noop,
+            }
+        },
+        Mode::OpenWith => {
+            match terminal_event {
+                Event::Key(keyevent) => {
+                    match keyevent.modifiers {
+                        KeyModifiers::CONTROL => match keyevent.code {
+                            KeyCode::Char('p') => Action::TryCursorMoveUp,
+                            KeyCode::Char('n') => Action::TryCursorMoveDown,
+                            _ => Action::Noop,
+                        },
+                        _ => match keyevent.code {
+                            KeyCode::Esc => Action::ExitOpenWithMode,
+                            KeyCode::Char(c) => Action::SetOpenWithText(format!("{}{}", m.open_with_text, c)),
+                            KeyCode::Backspace => {
+                                let mut chars = m.open_with_text.chars();
+                                chars.next_back();
+                                Action::SetOpenWithText(chars.as_str().to_string())
+                            },
+                            KeyCode::Up => Action::TryCursorMoveUp,
+                            KeyCode::Down => Action::TryCursorMoveDown,
+                            KeyCode::Enter => Action::OpenWithChoiceUnderCursor,
+                            _ => Action::Noop,
+                        }
+                    }
+                },
+                _ => Action::Noop,
+            }
+        },
+    }
+}
+
+// separate from update() so the command palette can run actions without a keypress
+fn update_with_action(m: &mut Model, action: Action) -> UpdateResult {
+    let action = match action {
+        Action::TrashSelected => Action::TrashPaths(selected_paths(m)),
+        Action::DeleteSelectedPermanently => Action::DeletePaths(selected_paths(m)),
+        _ => action,
+    };
+    // file operations wait for an explicit y/n first, unless turned off in config
+    if let Some(prompt) = confirmation_prompt(m, &action, None) {
+        // walking big dirs takes a while, so the totals are filled in once they're known
+        if let Some(paths) = paths_to_measure(m, &action) {
+            let messages = m.messages.clone();
+            std::thread::spawn(move || {
+                let (num_files, num_bytes) = file_ops::measure(&paths);
+                let _ = messages.send(Message::SelectionMeasured { paths, num_files, num_bytes });
+            });
+        }
+        m.pending_confirmation = Some(Confirmation { prompt, action });
+        return UpdateResult::Continue;
+    }
+    apply_action(m, action)
+}
+
+// eg. "move 3 entries (45 files, 1.2 GB) from /src to /dst?", or just "move 3 entries ..."
+// until the files have been counted. None if the action can go ahead without asking
+fn confirmation_prompt(m: &Model, action: &Action, totals: Option<(usize, u64)>) -> Option<String> {
+    if let Some(job) = &m.running_job {
+        return match action {
+            Action::Quit => Some(format!("still {}. cancel it and quit?", job.description)),
+            // it'll say it's busy without asking first
+            _ => None,
+        };
+    }
+    let describe_selection = |paths: &[PathBuf]| {
+        let entries = describe_count(paths.len(), "entry", "entries");
+        match totals {
+            Some((num_files, num_bytes)) => format!("{} ({}, {})", entries, describe_count(num_files, "file", "files"), FileSize(num_bytes).to_string().trim()),
+            None => entries,
+        }
+    };
+    match action {
+        Action::Paste => {
+            let register = m.yank_register.as_ref()?;
+            match register.cut {
+                true if m.config.confirm_move => {
+                    let from = register.paths.first().and_then(|path| path.parent()).unwrap_or(&m.cwd);
+                    Some(format!("move {} from {} to {}?", describe_selection(&register.paths), from.display(), m.cwd.display()))
+                },
+                false if m.config.confirm_copy => Some(format!("copy {} to {}?", describe_selection(&register.paths), m.cwd.display())),
+                _ => None,
+            }
+        },
+        Action::TrashPaths(paths) if m.config.confirm_trash => {
+            if paths.is_empty() { return None; }
+            Some(format!("move {} in {} to trash?", describe_selection(paths), m.cwd.display()))
+        },
+        // always asks, there's no getting these back
+        Action::DeletePaths(paths) => {
+            if paths.is_empty() { return None; }
+            Some(format!("permanently delete {} in {}? this can't be undone", describe_selection(paths), m.cwd.display()))
+        },
+        Action::Undo if m.config.confirm_undo => {
+            let batch = journal::last_batch(&m.journal_filepath).ok()??;
+            Some(format!("undo \"{}\" from {}?", batch.description, FileDate(batch.date).to_string().trim()))
+        },
+        _ => None,
+    }
+}
+
+// the paths that confirmation_prompt() summarizes, if any
+fn paths_to_measure(m: &Model, action: &Action) -> Option<Vec<PathBuf>> {
+    match action {
+        Action::Paste => m.yank_register.as_ref().map(|register| register.paths.clone()),
+        Action::TrashPaths(paths) | Action::DeletePaths(paths) => Some(paths.clone()),
+        _ => None,
+    }
+}
+
+// update state
+fn apply_action(m: &mut Model, action: Action) -> UpdateResult {
+    match action {
+        Action::GotoDir(pathbuf) => {
+            goto_dir(m, pathbuf);
+            UpdateResult::Continue
+        },
+        Action::SetFilterText(text) => {
+            // m.mode = match text.is_empty() {
+            //     true => Mode::Normal,
+            //     false => Mode::Filter,
+            // };
+            m.filter_text = text;
+            m.list_view.reset_with_items(filter_entries(&m.sorted_entries, &m.filter_text));
+            UpdateResult::Continue
+        },
+        Action::SelectEntryUnderCursor => {
+            // if no cursor, cant do anything
+            if m.list_view.items.is_empty() { return UpdateResult::Continue; }
+
+            let entry = &m.list_view.items[m.list_view.cursor_index];
+
+            if entry.is_dir {
+                goto_dir(m, entry.path.clone());
+            } else {
+                let path = entry.path.clone();
+                let opener = opener::choose_opener(&m.config.open_rules, &path);
+                return open_file(m, &opener, &path);
+            }
+            UpdateResult::Continue
+        },
+        Action::ChangeSortOrder(attribute) => {
+            // TODO - consider preserving hovered entry by finding it in the new sorted vec,
+            // and updating cursor_index accordingly,
+            // instead of just resetting to top
+            m.cwd_sort.attribute = attribute;
+            m.cwd_sort.ascending = false;
+            m.sorted_entries = sort_entries(&m.sorted_entries, m.cwd_sort);
+            m.list_view.reset_with_items(m.sorted_entries.clone());
+            change_history(m, HistoryChange::Sort(m.cwd.display().to_string(), m.cwd_sort));
+            UpdateResult::Continue
+        },
+        Action::ReverseSort => {
+            m.cwd_sort.ascending = !m.cwd_sort.ascending;
+            m.sorted_entries = sort_entries(&m.sorted_entries, m.cwd_sort);
+            m.list_view.reset_with_items(m.sorted_entries.clone());
+            change_history(m, HistoryChange::Sort(m.cwd.display().to_string(), m.cwd_sort));
+            UpdateResult::Continue
+        },
+        Action::TryCursorMoveUp => {
+            match m.mode {
+                Mode::Jump | Mode::EditHistory => m.jump_list.decrement_cursor(),
+                Mode::CommandPalette => m.palette_list.decrement_cursor(),
+                Mode::OpenWith => m.open_with_list.decrement_cursor(),
+                _ => m.list_view.decrement_cursor(),
+            };
+            UpdateResult::Continue
+        },
+        Action::TryCursorMoveDown => {
+            match m.mode {
+                Mode::Jump | Mode::EditHistory => m.jump_list.increment_cursor(),
+                Mode::CommandPalette => m.palette_list.increment_cursor(),
+                Mode::OpenWith => m.open_with_list.increment_cursor(),
+                _ => m.list_view.increment_cursor(),
+            };
+            UpdateResult::Continue
+        },
+        Action::StartJumpMode => {
+            m.mode = Mode::Jump;
+            m.jump_text = "".to_string();
+            m.missing_dirs = find_missing_dirs(&m.history);
+            m.jump_list.reset_with_items(rank_jump_list(m));
+            UpdateResult::Continue
+        },
+        Action::SetJumpText(text) => {
+            m.jump_text = text;
+            m.jump_list.reset_with_items(rank_jump_list(m));
+            UpdateResult::Continue
+        },
+        Action::StartEditHistoryMode => {
+            // edits whatever the jump list is currently showing, so type to narrow it down first
+            m.mode = Mode::EditHistory;
+            rerank_jump_list_keeping_cursor(m);
+            UpdateResult::Continue
+        },
+        Action::ExitEditHistoryMode => {
+            // back to jumping, still narrowed down by the same jump text
+            m.mode = Mode::Jump;
+            rerank_jump_list_keeping_cursor(m);
+            UpdateResult::Continue
+        },
+        Action::JumpToDir(path) => {
+            // the list only knows which dirs were missing when jump mode started, so check again
+            let pathbuf = PathBuf::from(&path);
+            if pathbuf.is_dir() {
+                goto_dir(m, pathbuf);
+            } else {
+                m.missing_dirs.insert(path.clone());
+                m.jump_list.replace_items(rank_jump_list(m));
+                m.status_message = format!("{} is missing, Ctrl+E and d to remove it from the list", path);
+            }
+            UpdateResult::Continue
+        },
+        Action::RemoveFromHistory(path) => {
+            change_history(m, HistoryChange::Remove(path));
+            m.jump_list.replace_items(rank_jump_list(m));
+            UpdateResult::Continue
+        },
+        Action::TogglePinInHistory(path) => {
+            let pinned = m.history.get(&path).map(|data| data.pinned).unwrap_or(false);
+            change_history(m, HistoryChange::Pin(path, !pinned));
+            m.jump_list.replace_items(rank_jump_list(m));
+            UpdateResult::Continue
+        },
+        Action::ResetHistoryScore(path) => {
+            change_history(m, HistoryChange::Reset(path));
+            m.jump_list.replace_items(rank_jump_list(m));
+            UpdateResult::Continue
+        },
+        Action::ExitJumpMode => {
+            m.mode = Mode::Filter;
+            UpdateResult::Continue
+        },
+        Action::StartFilterMode => {
+            m.mode = Mode::Filter;
+            UpdateResult::Continue
+        },
+        Action::ToggleMarkUnderCursor => {
+            // move along afterwards, so marking several entries in a row is just repeated presses
+            toggle_mark_under_cursor(m);
+            m.list_view.increment_cursor();
+            UpdateResult::Continue
+        },
+        Action::MarkRangeToCursor => {
+            mark_range_to_cursor(m);
+            UpdateResult::Continue
+        },
+        Action::MarkAll => {
+            mark_all(m);
+            UpdateResult::Continue
+        },
+        Action::InvertMarks => {
+            invert_marks(m);
+            UpdateResult::Continue
+        },
+        Action::ClearMarks => {
+            clear_marks(m);
+            UpdateResult::Continue
+        },
+        Action::YankSelected | Action::CutSelected => {
+            let paths = selected_paths(m);
+            if paths.is_empty() { return UpdateResult::Continue; }
+            let cut = matches!(action, Action::CutSelected);
+            m.status_message = format!("{} {}", if cut { "cut" } else { "copied" }, describe_count(paths.len(), "entry", "entries"));
+            m.yank_register = Some(YankRegister { paths, cut });
+            // so the next command doesn't act on them again by accident
+            clear_marks(m);
+            UpdateResult::Continue
+        },
+        Action::Paste => {
+            let Some(register) = m.yank_register.clone() else {
+                m.status_message = "nothing to paste, copy or cut something first".to_string();
+                return UpdateResult::Continue;
+            };
+            let to_dir = m.cwd.clone();
+            let job = match register.cut {
+                true => Job::Move { paths: register.paths, to_dir },
+                false => Job::Copy { paths: register.paths, to_dir },
+            };
+            // cut entries leave the register once they've been moved, in finish_job()
+            start_job(m, job);
+            UpdateResult::Continue
+        },
+        Action::TrashSelected => apply_action(m, Action::TrashPaths(selected_paths(m))),
+        Action::DeleteSelectedPermanently => apply_action(m, Action::DeletePaths(selected_paths(m))),
+        Action::TrashPaths(paths) => {
+            if trash::trash_files_dir().as_ref() == Some(&m.cwd) {
+                m.status_message = "already in the trash. delete permanently instead".to_string();
+                return UpdateResult::Continue;
+            }
+            if paths.is_empty() { return UpdateResult::Continue; }
+            if start_job(m, Job::Trash { paths }) {
+                clear_marks(m);
+            }
+            UpdateResult::Continue
+        },
+        Action::DeletePaths(paths) => {
+            if paths.is_empty() { return UpdateResult::Continue; }
+            if start_job(m, Job::Delete { paths }) {
+                clear_marks(m);
+            }
+            UpdateResult::Continue
+        },
+        Action::CancelJob => {
+            match &m.running_job {
+                Some(job) => {
+                    job.cancel();
+                    m.status_message = "cancelling...".to_string();
+                },
+                None => m.status_message = "nothing to cancel".to_string(),
+            };
+            UpdateResult::Continue
+        },
+        Action::GotoTrash => {
+            match trash::trash_files_dir() {
+                Some(dir) if dir.is_dir() => goto_dir(m, dir),
+                _ => m.status_message = "trash is empty".to_string(),
+            };
+            UpdateResult::Continue
+        },
+        // only works from inside the trash dir, since that's where you can see what's in there
+        Action::RestoreSelectedFromTrash => {
+            if trash::trash_files_dir().as_ref() != Some(&m.cwd) {
+                m.status_message = "go to the trash first to pick what to restore".to_string();
+                return UpdateResult::Continue;
+            }
+            let paths = selected_paths(m);
+            if paths.is_empty() { return UpdateResult::Continue; }
+            if start_job(m, Job::Restore { paths }) {
+                clear_marks(m);
+            }
+            UpdateResult::Continue
+        },
+        Action::BatchRename => start_batch_rename(m),
+        Action::Undo => {
+            if let Some(job) = &m.running_job {
+                m.status_message = format!("still {}, wait or cancel it first", job.description);
+                return UpdateResult::Continue;
+            }
+            m.status_message = undo(m);
+            refresh_cwd(m);
+            UpdateResult::Continue
+        },
+        Action::StartNewFilePrompt => {
+            let cwd = m.cwd.clone();
+            m.prompt = Some(Prompt::new(
+                "new file name:",
+                "",
+                move |name| validate_new_name(&cwd, name),
+                { let cwd = m.cwd.clone(); move |name| Action::CreateFile(cwd.join(name)) },
+            ));
+            UpdateResult::Continue
+        },
+        Action::StartNewDirPrompt => {
+            let cwd = m.cwd.clone();
+            m.prompt = Some(Prompt::new(
+                "new folder name:",
+                "",
+                move |name| validate_new_name(&cwd, name),
+                { let cwd = m.cwd.clone(); move |name| Action::CreateDir(cwd.join(name)) },
+            ));
+            UpdateResult::Continue
+        },
+        Action::StartRenamePrompt => {
+            let Some(entry) = m.list_view.item_under_cursor() else { return UpdateResult::Continue; };
+            let original_path = entry.path.clone();
+            let original_name = entry.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let cwd = m.cwd.clone();
+            m.prompt = Some(Prompt::new(
+                "rename to:",
+                &original_name.clone(),
+                move |name| match name == original_name {
+                    true => Ok(()),
+                    false => validate_new_name(&cwd, name),
+                },
+                move |name| Action::RenamePath(original_path.clone(), original_path.with_file_name(name)),
+            ));
+            UpdateResult::Continue
+        },
+        Action::CreateFile(path) => {
+            let result = std::fs::OpenOptions::new().write(true).create_new(true).open(&path).map(|_file| ());
+            m.status_message = match result {
+                Ok(()) => record_in_journal(m, "created", vec![JournalOp::Created(path.clone())], &[]),
+                Err(err) => describe_error(&path, err),
+            };
+            refresh_cwd(m);
+            move_cursor_to_path(m, &path);
+            UpdateResult::Continue
+        },
+        Action::CreateDir(path) => {
+            m.status_message = match std::fs::create_dir(&path) {
+                Ok(()) => record_in_journal(m, "created", vec![JournalOp::Created(path.clone())], &[]),
+                Err(err) => describe_error(&path, err),
+            };
+            refresh_cwd(m);
+            move_cursor_to_path(m, &path);
+            UpdateResult::Continue
+        },
+        Action::RenamePath(from, to) => {
+            if from == to { return UpdateResult::Continue; }
+            // rename() would silently replace whatever's there
+            let result = match std::fs::symlink_metadata(&to) {
+                Ok(_) => Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "already exists")),
+                Err(_) => std::fs::rename(&from, &to),
+            };
+            m.status_message = match result {
+                Ok(()) => record_in_journal(m, "renamed", vec![JournalOp::Renamed { from, to: to.clone() }], &[]),
+                Err(err) => describe_error(&to, err),
+            };
+            refresh_cwd(m);
+            move_cursor_to_path(m, &to);
+            UpdateResult::Continue
+        },
+        Action::StartNormalMode => {
+            m.mode = Mode::Normal;
+            UpdateResult::Continue
+        },
+        Action::StartCommandPaletteMode => {
+            m.palette_origin = m.mode;
+            m.mode = Mode::CommandPalette;
+            m.palette_text = "".to_string();
+            m.palette_list.reset_with_items(filter_palette_commands(palette_commands(m, m.palette_origin), &m.palette_text));
+            UpdateResult::Continue
+        },
+        Action::SetPaletteText(text) => {
+            m.palette_text = text;
+            m.palette_list.reset_with_items(filter_palette_commands(palette_commands(m, m.palette_origin), &m.palette_text));
+            UpdateResult::Continue
+        },
+        Action::ExitCommandPaletteMode => {
+            m.mode = m.palette_origin;
+            UpdateResult::Continue
+        },
+        Action::RunPaletteCommandUnderCursor => {
+            // go back to where the palette was opened from, then act like the keybinding was pressed there
+            let action = match m.palette_list.item_under_cursor() {
+                Some(command) => command.action.clone(),
+                None => return UpdateResult::Continue,
+            };
+            m.mode = m.palette_origin;
+            update_with_action(m, action)
+        },
+        Action::StartOpenWithMode => {
+            let Some(entry) = m.list_view.item_under_cursor() else { return UpdateResult::Continue; };
+            let path = entry.path.clone();
+            let last_choice = opener::choice_key(&path).and_then(|extension| opener::read_last_choices(&m.open_with_filepath).remove(&extension));
+            m.open_with_choices = opener::open_with_choices(&m.config, &path, last_choice.as_ref());
+            m.open_with_path = path;
+            m.open_with_origin = m.mode;
+            m.mode = Mode::OpenWith;
+            m.open_with_text = "".to_string();
+            m.open_with_list.reset_with_items(filter_open_with_choices(&m.open_with_choices, &m.open_with_text));
+            UpdateResult::Continue
+        },
+        Action::SetOpenWithText(text) => {
+            m.open_with_text = text;
+            m.open_with_list.reset_with_items(filter_open_with_choices(&m.open_with_choices, &m.open_with_text));
+            UpdateResult::Continue
+        },
+        Action::ExitOpenWithMode => {
+            m.mode = m.open_with_origin;
+            UpdateResult::Continue
+        },
+        Action::OpenWithChoiceUnderCursor => {
+            let Some(opener) = m.open_with_list.item_under_cursor().cloned() else { return UpdateResult::Continue; };
+            m.mode = m.open_with_origin;
+            let path = m.open_with_path.clone();
+            if let Err(err) = opener::remember_choice(&m.open_with_filepath, &path, &opener.name) {
+                log::info!("couldn't remember open-with choice: {}", err);
+            }
+            open_file(m, &opener, &path)
+        },
+        Action::StartSubshell => start_subshell(m),
+        Action::StartPager => {
+            let Some(entry) = m.list_view.item_under_cursor() else { return UpdateResult::Continue; };
+            if entry.is_dir {
+                m.status_message = "can only view files".to_string();
+                return UpdateResult::Continue;
+            }
+            match Pager::open(&entry.path, m.rows - NUM_ROWS_OUTSIDE_LISTVIEW) {
+                Ok(pager) => {
+                    m.pager = Some(pager);
+                    m.pager_origin = m.mode;
+                    m.mode = Mode::Pager;
+                },
+                Err(err) => m.status_message = describe_error(&entry.path, err),
+            };
+            UpdateResult::Continue
+        },
+        Action::ExitPager => {
+            m.pager = None;
+            m.mode = m.pager_origin;
+            UpdateResult::Continue
+        },
+        Action::PagerScrollDown(num_lines) => {
+            if let Some(pager) = &mut m.pager { pager.scroll_down(num_lines); }
+            UpdateResult::Continue
+        },
+        Action::PagerScrollUp(num_lines) => {
+            if let Some(pager) = &mut m.pager { pager.scroll_up(num_lines); }
+            UpdateResult::Continue
+        },
+        Action::PagerGotoStart => {
+            if let Some(pager) = &mut m.pager { pager.goto_start(); }
+            UpdateResult::Continue
+        },
+        Action::PagerGotoEnd => {
+            if let Some(pager) = &mut m.pager { pager.goto_end(); }
+            UpdateResult::Continue
+        },
+        Action::PagerToggleHex => {
+            if let Some(pager) = &mut m.pager { pager.toggle_hex(); }
+            UpdateResult::Continue
+        },
+        Action::StartPagerSearchPrompt => {
+            m.prompt = Some(Prompt::new(
+                "search:",
+                "",
+                |text| match text.is_empty() {
+                    true => Err("type something to search for".to_string()),
+                    false => Ok(()),
+                },
+                Action::PagerSearch,
+            ));
+            UpdateResult::Continue
+        },
+        Action::PagerSearch(query) => {
+            if let Some(pager) = &mut m.pager {
+                pager.query = query;
+                m.status_message = pager.search(true, false);
+            }
+            UpdateResult::Continue
+        },
+        Action::PagerSearchAgain { forward } => {
+            if let Some(pager) = &mut m.pager {
+                m.status_message = pager.search(forward, true);
+            }
+            UpdateResult::Continue
+        },
+        Action::TogglePreview => {
+            m.preview_visible = !m.preview_visible;
+            m.previews.clear();
+            UpdateResult::Continue
+        },
+        Action::RunCustomCommand(command) => {
+            run_custom_command(m, &command);
+            UpdateResult::Continue
+        },
+        Action::Noop => UpdateResult::Continue,
+        Action::Quit => UpdateResult::Finish,
+    }
+}
+
+// reread the cwd after something may have changed inside it, without losing your place
+fn refresh_cwd(m: &mut Model) {
+    let path_under_cursor = m.list_view.item_under_cursor().map(|entry| entry.path.clone());
+    m.entries_before_refresh = Some(m.sorted_entries
+        .drain(..)
+        .map(|entry| (entry.path.clone(), entry))
+        .collect());
+    // always from the disk, since the mtime doesn't change when a file inside is edited
+    load_cwd(m, false);
+    highlight_changes(m, |entry| !entry.loading);
+    if m.metadata_loader.is_none() {
+        m.entries_before_refresh = None;
+    }
+    show_refreshed_entries(m, path_under_cursor);
+}
+
+// like refresh_cwd, but only rereads the entries the watcher saw change
+fn refresh_entries(m: &mut Model, paths: &[PathBuf]) {
+    let path_under_cursor = m.list_view.item_under_cursor().map(|entry| entry.path.clone());
+    let changed = paths.iter().collect::<HashSet<&PathBuf>>();
+    // before the entries, so if anything changes while they're read, the cached copy looks out of date
+    m.cwd_modified = std::fs::metadata(&m.cwd).and_then(|metadata| metadata.modified()).ok();
+    let (before, mut entries) = m.sorted_entries
+        .drain(..)
+        .partition::<Vec<Entry>, _>(|entry| changed.contains(&entry.path));
+    m.entries_before_refresh = Some(before.into_iter().map(|entry| (entry.path.clone(), entry)).collect());
+    for path in paths {
+        // even if the size and date are the same, eg. after a chmod
+        m.previews.remove(path);
+        entries.extend(dir_loader::read_entry(path));
+    }
+    m.sorted_entries = sort_entries(&entries, m.cwd_sort);
+    highlight_changes(m, |entry| changed.contains(&entry.path));
+    m.entries_before_refresh = None;
+    cache_cwd(m);
+    show_refreshed_entries(m, path_under_cursor);
+}
+
+fn show_refreshed_entries(m: &mut Model, path_under_cursor: Option<PathBuf>) {
+    m.list_view.replace_items(filter_entries(&m.sorted_entries, &m.filter_text));
+    // forget marks on entries that don't exist anymore
+    let existing_paths = m.sorted_entries.iter().map(|entry| &entry.path).collect::<HashSet<&PathBuf>>();
+    m.marked_paths.retain(|path| existing_paths.contains(path));
+    if let Some(index) = m.list_view.items.iter().position(|entry| Some(&entry.path) == path_under_cursor.as_ref()) {
+        m.list_view.set_cursor(index);
+    }
+}
+
+// how long to read metadata before showing "loading…" instead. most dirs are done by then,
+// so they show up complete, without flashing
+const METADATA_WAIT : Duration = Duration::from_millis(50);
+
+// lists the names in the cwd, then reads their size and date, in the background if it takes a while.
+// use_cache is for when a dir that hasn't been touched since last time can be shown as it was
+fn load_cwd(m: &mut Model, use_cache: bool) {
+    m.metadata_loader = None;
+    // before the entries, so if anything changes while they're read, the cached copy looks out of date
+    m.cwd_modified = std::fs::metadata(&m.cwd).and_then(|metadata| metadata.modified()).ok();
+    let cached = match (use_cache, m.cwd_modified) {
+        (true, Some(modified)) => m.dir_cache.get(&m.cwd, modified),
+        _ => None,
+    };
+    if let Some((entries, sort)) = cached {
+        m.sorted_entries = match sort == m.cwd_sort {
+            true => entries,
+            false => sort_entries(&entries, m.cwd_sort),
+        };
+        return;
+    }
+
+    let mut entries = sort_entries(&dir_loader::read_entry_names(&m.cwd), m.cwd_sort);
+    let num_loaded = dir_loader::read_metadata_until(&mut entries, Instant::now() + METADATA_WAIT);
+    m.metadata_loader = match num_loaded < entries.len() {
+        true => Some(MetadataLoader::start(&entries[num_loaded..], m.messages.clone())),
+        false => None,
+    };
+    m.sorted_entries = match m.metadata_loader {
+        // sizes and dates are all there, so they can be sorted by already
+        None if m.cwd_sort.attribute != EntryAttribute::Name => sort_entries(&entries, m.cwd_sort),
+        _ => entries,
+    };
+    if m.metadata_loader.is_none() {
+        cache_cwd(m);
+    }
+}
+
+fn cache_cwd(m: &mut Model) {
+    if let Some(modified) = m.cwd_modified {
+        m.dir_cache.insert(&m.cwd, modified, m.cwd_sort, &m.sorted_entries);
+    }
+}
+
+fn receive_message(m: &mut Model, message: Message) -> UpdateResult {
+    match message {
+        Message::MetadataLoaded { loader_id, loaded, finished } => receive_metadata(m, loader_id, loaded, finished),
+        Message::PreviewLoaded(path, preview) => receive_preview(m, path, preview),
+        Message::JobFinished => {
+            finish_job(m);
+            UpdateResult::Continue
+        },
+        Message::CustomCommandFinished(said) => {
+            m.status_message = said;
+            // it may well have changed the cwd, eg. `touch` or `tar -x`
+            refresh_cwd(m);
+            UpdateResult::Continue
+        },
+        Message::SelectionMeasured { paths, num_files, num_bytes } => {
+            let Some(confirmation) = &m.pending_confirmation else { return UpdateResult::Unchanged; };
+            // from a prompt that's been answered since
+            if paths_to_measure(m, &confirmation.action).as_ref() != Some(&paths) {
+                return UpdateResult::Unchanged;
+            }
+            if let Some(prompt) = confirmation_prompt(m, &confirmation.action, Some((num_files, num_bytes))) {
+                if let Some(confirmation) = &mut m.pending_confirmation {
+                    confirmation.prompt = prompt;
+                }
+            }
+            UpdateResult::Continue
+        },
+        // from the watcher of a dir that's been left since
+        Message::CwdChanged { watcher_id, .. } if watcher_id != m.watcher.id => UpdateResult::Unchanged,
+        // entries that are still loading would need their metadata matched up, so those get a full refresh
+        Message::CwdChanged { changed: Some(paths), .. } if m.metadata_loader.is_none() => {
+            refresh_entries(m, &paths);
+            UpdateResult::Continue
+        },
+        Message::CwdChanged { .. } => {
+            refresh_cwd(m);
+            UpdateResult::Continue
+        },
+    }
+}
+
+fn receive_metadata(m: &mut Model, loader_id: usize, loaded: Vec<LoadedMetadata>, finished: bool) -> UpdateResult {
+    // left over from a dir that's been left or reread since
+    if m.metadata_loader.as_ref().map(|loader| loader.id) != Some(loader_id) {
+        return UpdateResult::Unchanged;
+    }
+    if finished {
+        m.metadata_loader = None;
+    }
+
+    let loaded = loaded
+        .into_iter()
+        .map(|metadata| (metadata.path, (metadata.size, metadata.date)))
+        .collect::<HashMap<PathBuf, (Option<FileSize>, Option<FileDate>)>>();
+    for entry in m.sorted_entries.iter_mut().chain(m.list_view.items.iter_mut()).filter(|entry| entry.loading) {
+        if let Some((size, date)) = loaded.get(&entry.path) {
+            entry.size = size.clone();
+            entry.date = date.clone();
+            entry.loading = false;
+        }
+    }
+    highlight_changes(m, |entry| loaded.contains_key(&entry.path));
+    if finished {
+        m.entries_before_refresh = None;
+    }
+    // sorting by size or date has to wait until they're all there.
+    // only once, so the list doesn't keep shuffling while you're looking at it
+    if finished && m.cwd_sort.attribute != EntryAttribute::Name {
+        let path_under_cursor = m.list_view.item_under_cursor().map(|entry| entry.path.clone());
+        m.sorted_entries = sort_entries(&m.sorted_entries, m.cwd_sort);
+        m.list_view.replace_items(filter_entries(&m.sorted_entries, &m.filter_text));
+        if let Some(index) = m.list_view.items.iter().position(|entry| Some(&entry.path) == path_under_cursor.as_ref()) {
+            m.list_view.set_cursor(index);
+        }
+    }
+    if finished {
+        cache_cwd(m);
+    }
+    UpdateResult::Continue
+}
+
+// how long new and changed rows stay highlighted after a refresh
+const HIGHLIGHT_DURATION : Duration = Duration::from_secs(2);
+
+// highlights entries that weren't there before the last refresh, or whose size or date changed.
+// only checks the ones matching `which`, eg. the ones whose metadata just came in
+fn highlight_changes(m: &mut Model, which: impl Fn(&Entry) -> bool) {
+    let Some(entries_before) = &m.entries_before_refresh else { return; };
+    let now = Instant::now();
+    for entry in m.sorted_entries.iter().filter(|entry| which(entry)) {
+        let changed = match entries_before.get(&entry.path) {
+            None => true,
+            // still loading back then, so there's nothing to compare with
+            Some(before) if before.loading => false,
+            Some(before) => before.size != entry.size || before.date != entry.date,
+        };
+        if changed {
+            m.recently_changed.insert(entry.path.clone(), now);
+            m.previews.remove(&entry.path);
+        }
+    }
+}
+
+// runs a file operation on another thread, so the ui can keep going.
+// returns false if it can't start yet
+fn start_job(m: &mut Model, job: Job) -> bool {
+    if let Some(running_job) = &m.running_job {
+        m.status_message = format!("still {}, wait or cancel it first", running_job.description);
+        return false;
+    }
+    m.running_job = Some(RunningJob::start(job, m.messages.clone()));
+    true
+}
+
+fn tick(m: &mut Model) -> UpdateResult {
+    let num_highlighted = m.recently_changed.len();
+    m.recently_changed.retain(|_, changed| changed.elapsed() < HIGHLIGHT_DURATION);
+    let highlights_faded = m.recently_changed.len() < num_highlighted;
+    match &m.running_job {
+        // redraw the progress
+        Some(_) => UpdateResult::Continue,
+        None if highlights_faded => UpdateResult::Continue,
+        None => UpdateResult::Unchanged,
+    }
+}
+
+// --- preview --- //
+
+// plenty for going back and forth, while a huge dir can't fill up memory with previews
+const MAX_CACHED_PREVIEWS : usize = 500;
+
+fn request_preview(m: &mut Model) {
+    if !m.preview_visible || !matches!(m.mode, Mode::Filter | Mode::Normal) { return; }
+    let Some(entry) = m.list_view.item_under_cursor() else { return; };
+    if m.previews.contains_key(&entry.path) || m.preview_requested.as_ref() == Some(&entry.path) { return; }
+    m.previewer.request(&entry.path, SortBy::default());
+    m.preview_requested = Some(entry.path.clone());
+}
+
+fn receive_preview(m: &mut Model, path: PathBuf, preview: Preview) -> UpdateResult {
+    if m.preview_requested.as_ref() == Some(&path) {
+        m.preview_requested = None;
+    }
+    if m.previews.len() >= MAX_CACHED_PREVIEWS {
+        let oldest = m.previews
+            .iter()
+            .min_by_key(|(_, (_, loaded))| *loaded)
+            .map(|(path, _)| path.clone());
+        if let Some(oldest) = oldest {
+            m.previews.remove(&oldest);
+        }
+    }
+    m.previews.insert(path, (preview, Instant::now()));
+    UpdateResult::Continue
+}
+
+fn finish_job(m: &mut Model) {
+    let Some(job) = m.running_job.take() else { return; };
+    let result = job.wait();
+    // cut entries won't be where they were anymore. whatever failed to move can be pasted again
+    if let Some(register) = &mut m.yank_register {
+        let moved = result.ops
+            .iter()
+            .filter_map(|op| match op {
+                JournalOp::Moved { from, .. } => Some(from),
+                _ => None,
+            })
+            .collect::<HashSet<&PathBuf>>();
+        register.paths.retain(|path| !moved.contains(path));
+        if register.paths.is_empty() {
+            m.yank_register = None;
+        }
+    }
+    let message = match result.ops.is_empty() {
+        // eg. permanent deletes, which can't be undone
+        true => describe_results(result.verb, result.num_succeeded, &result.errors),
+        false => record_in_journal(m, result.verb, result.ops, &result.errors),
+    };
+    m.status_message = match result.cancelled {
+        true => format!("cancelled, {}", message),
+        false => message,
+    };
+    refresh_cwd(m);
+}
+
+// renames marked entries (or everything visible) by editing their names in $EDITOR.
+// returns a message for the status line
+fn start_batch_rename(m: &mut Model) -> UpdateResult {
+    let originals = match m.marked_paths.is_empty() {
+        true => m.list_view.items.iter().map(|entry| entry.path.clone()).collect(),
+        false => selected_paths(m),
+    };
+    if originals.is_empty() {
+        m.status_message = "nothing to rename".to_string();
+        return UpdateResult::Continue;
+    }
+    let names = originals
+        .iter()
+        .map(|path| path.file_name().unwrap_or_default().to_string_lossy().to_string())
+        .collect::<Vec<String>>();
+    // one name per line, so a name with a newline would throw off every line after it
+    if names.iter().any(|name| name.contains('\n')) {
+        m.status_message = "can't batch rename names with newlines in them".to_string();
+        return UpdateResult::Continue;
+    }
+
+    // not in the shared temp dir, where anyone could plant a symlink at a name this predictable
+    let temp_path = m.data_dir.join(format!("fmin.rename_{}.txt", std::process::id()));
+    let written = std::fs::create_dir_all(&m.data_dir).and_then(|()| std::fs::write(&temp_path, names.join("\n") + "\n"));
+    if let Err(err) = written {
+        m.status_message = format!("couldn't write {}: {}", temp_path.display(), err);
+        return UpdateResult::Continue;
+    }
+    // $EDITOR can have args, like `code --wait`
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut editor_words = editor.split_whitespace();
+    let mut command = std::process::Command::new(editor_words.next().unwrap_or("vi"));
+    command.args(editor_words).arg(&temp_path).current_dir(&m.cwd);
+
+    m.after_child_exits = Some(AfterChildExits::BatchRename { originals, temp_path, editor });
+    UpdateResult::RunInTerminal(command)
+}
+
+// renames everything once $EDITOR exits. returns a message for the status line
+fn finish_batch_rename(m: &mut Model, originals: Vec<PathBuf>, temp_path: PathBuf, editor: String, status: std::io::Result<ExitStatus>) -> String {
+    let edited_text = std::fs::read_to_string(&temp_path);
+    let _ = std::fs::remove_file(&temp_path);
+
+    let edited_text = match (status, edited_text) {
+        (Ok(status), Ok(text)) if status.success() => text,
+        (Ok(status), Ok(_)) => return format!("{} exited with {}, so nothing was renamed", editor, status),
+        (Err(err), _) => return format!("couldn't run {}: {}", editor, err),
+        (_, Err(err)) => return format!("couldn't read edited names: {}", err),
+    };
+    let renames = match file_ops::plan_renames(&originals, &edited_text) {
+        Ok(renames) => renames,
+        Err(msg) => return msg,
+    };
+    let (done, errors) = file_ops::apply_renames(&renames);
+    clear_marks(m);
+    let ops = done
+        .into_iter()
+        .map(|(from, to)| JournalOp::Renamed { from, to })
+        .collect();
+    record_in_journal(m, "renamed", ops, &errors)
+}
+
+// opens in the background right away, or hands the terminal over to the opener
+fn open_file(m: &mut Model, opener: &Opener, path: &Path) -> UpdateResult {
+    match opener::open(opener, path, &m.cwd) {
+        opener::Opening::Detached(message) => {
+            m.status_message = message;
+            UpdateResult::Continue
+        },
+        opener::Opening::InTerminal(command) => {
+            m.after_child_exits = Some(AfterChildExits::Opened { command: opener.command.clone() });
+            UpdateResult::RunInTerminal(command)
+        },
+    }
+}
+
+// back from a program that had the terminal, like $EDITOR
+fn resume(m: &mut Model, status: std::io::Result<ExitStatus>) -> UpdateResult {
+    m.status_message = match m.after_child_exits.take() {
+        Some(AfterChildExits::BatchRename { originals, temp_path, editor }) => finish_batch_rename(m, originals, temp_path, editor, status),
+        Some(AfterChildExits::Opened { command }) => match status {
+            Ok(status) if status.success() => String::new(),
+            Ok(status) => format!("{} exited with {}", command, status),
+            Err(err) => format!("couldn't run {}: {}", command, err),
+        },
+        // a shell exits with whatever the last command did, which is nothing to report
+        Some(AfterChildExits::Subshell { selected_paths_file }) => {
+            let _ = std::fs::remove_file(selected_paths_file);
+            match status {
+                Ok(_) => String::new(),
+                Err(err) => format!("couldn't start a shell: {}", err),
+            }
+        },
+        None => String::new(),
+    };
+    // eg. an editor may have saved new files
+    refresh_cwd(m);
+    UpdateResult::Continue
+}
+
+// reverses the last batch of file operations, from this session or another one.
+// returns a message for the status line
+fn undo(m: &mut Model) -> String {
+    let batch = match journal::take_last_batch(&m.journal_filepath) {
+        Ok(Some(batch)) => batch,
+        Ok(None) => return "nothing to undo".to_string(),
+        Err(err) => return format!("couldn't read {}: {}", m.journal_filepath.display(), err),
+    };
+    let (num_undone, conflicts, not_undone) = journal::undo_batch(&batch);
+    let message = format!("undid \"{}\" from {}", batch.description, FileDate(batch.date).to_string().trim());
+    if conflicts.is_empty() {
+        return message;
+    }
+    let message = format!("{}, but {} couldn't be undone: {}", message, describe_count(conflicts.len(), "entry", "entries"), conflicts.join(", "));
+    // so undo can try them again once the conflicts are sorted out, eg. by moving something out of the way.
+    // record_batch() skips it if there's nothing left to retry
+    let rest = JournalBatch { ops: not_undone, ..batch };
+    match journal::record_batch(&m.journal_filepath, rest) {
+        Ok(()) => message,
+        Err(err) => format!("{} (can't retry, couldn't write {}: {})", message, m.journal_filepath.display(), err),
+    }
+}
+
+// remembers what a file operation did so it can be undone, and returns a message for the status line
+fn record_in_journal(m: &Model, verb: &str, ops: Vec<JournalOp>, errors: &[String]) -> String {
+    let message = describe_results(verb, ops.len(), errors);
+    let batch = JournalBatch {
+        date: Local::now(),
+        description: format!("{} {}", verb, describe_count(ops.len(), "entry", "entries")),
+        ops,
+    };
+    match journal::record_batch(&m.journal_filepath, batch) {
+        Ok(()) => message,
+        Err(err) => format!("{} (can't undo, couldn't write {}: {})", message, m.journal_filepath.display(), err),
+    }
+}
+
+fn answer_prompt(m: &mut Model, key: KeyCode) -> UpdateResult {
+    let Some(prompt) = &mut m.prompt else { return UpdateResult::Continue; };
+    match key {
+        KeyCode::Char(c) => {
+            prompt.text.push(c);
+            prompt.revalidate();
+        },
+        KeyCode::Backspace => {
+            prompt.text.pop();
+            prompt.revalidate();
+        },
+        KeyCode::Esc => m.prompt = None,
+        // checked once more, the dir may have changed since the last keypress
+        KeyCode::Enter => match (prompt.validate)(&prompt.text) {
+            Ok(()) => {
+                let action = (prompt.submit)(prompt.text.clone());
+                m.prompt = None;
+                return update_with_action(m, action);
+            },
+            // stay open, so the text can be fixed
+            Err(msg) => m.status_message = msg,
+        },
+        _ => (),
+    };
+    UpdateResult::Continue
+}
+
+// for anything about to be created in dir, like a new file or a renamed entry
+fn validate_new_name(dir: &Path, name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("type a name".to_string());
+    }
+    if name.contains('/') || name == "." || name == ".." {
+        return Err(format!("{} isn't a plain name", name));
+    }
+    if std::fs::symlink_metadata(dir.join(name)).is_ok() {
+        return Err(format!("{} already exists", name));
+    }
+    Ok(())
+}
+
+fn answer_confirmation(m: &mut Model, key: KeyCode) -> UpdateResult {
+    // only an explicit answer counts, so a stray keypress can't delete anything
+    match key {
+        KeyCode::Char('y') | KeyCode::Char('Y') => match m.pending_confirmation.take() {
+            Some(confirmation) => apply_action(m, confirmation.action),
+            None => UpdateResult::Continue,
+        },
+        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+            m.pending_confirmation = None;
+            m.status_message = "cancelled".to_string();
+            UpdateResult::Continue
+        },
+        _ => UpdateResult::Continue,
+    }
+}
+
+// eg. "notes.txt: Permission denied (os error 13)"
+fn describe_error(path: &Path, err: std::io::Error) -> String {
+    format!("{}: {}", path.file_name().unwrap_or(path.as_os_str()).to_string_lossy(), err)
+}
+
+// eg. "copied 3 entries, 1 failed: notes.txt: Permission denied (os error 13)"
+fn describe_results(verb: &str, num_succeeded: usize, errors: &[String]) -> String {
+    let message = format!("{} {}", verb, describe_count(num_succeeded, "entry", "entries"));
+    match errors.len() {
+        0 => message,
+        _ => format!("{}, {} failed: {}", message, errors.len(), errors.join(", ")),
+    }
+}
+
+// eg. "1 entry", "3 entries"
+fn describe_count(count: usize, singular: &str, plural: &str) -> String {
+    format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+fn is_marked(m: &Model, entry: &Entry) -> bool {
+    m.marked_paths.contains(&entry.path)
+}
+
+fn toggle_mark_under_cursor(m: &mut Model) {
+    let path = match m.list_view.item_under_cursor() {
+        Some(entry) => entry.path.clone(),
+        None => return,
+    };
+    if !m.marked_paths.remove(&path) {
+        m.marked_paths.insert(path.clone());
+    }
+    m.mark_anchor = Some(path);
+}
+
+// marks everything between the last toggled entry and the cursor, inclusive
+fn mark_range_to_cursor(m: &mut Model) {
+    let list = &m.list_view;
+    let anchor_index = m.mark_anchor
+        .as_ref()
+        .and_then(|anchor| list.items.iter().position(|entry| &entry.path == anchor));
+    let anchor_index = match anchor_index {
+        Some(index) => index,
+        // anchor was filtered out or never set, so there's no range; just mark this one
+        None => list.cursor_index,
+    };
+    let (start, end) = match anchor_index <= list.cursor_index {
+        true => (anchor_index, list.cursor_index),
+        false => (list.cursor_index, anchor_index),
+    };
+    for entry in list.items.iter().skip(start).take(end + 1 - start) {
+        m.marked_paths.insert(entry.path.clone());
+    }
+}
+
+// only affects the visible (filtered) entries
+fn mark_all(m: &mut Model) {
+    for entry in m.list_view.items.iter() {
+        m.marked_paths.insert(entry.path.clone());
+    }
+}
+
+fn invert_marks(m: &mut Model) {
+    for entry in m.list_view.items.iter() {
+        if !m.marked_paths.remove(&entry.path) {
+            m.marked_paths.insert(entry.path.clone());
+        }
+    }
+}
+
+fn clear_marks(m: &mut Model) {
+    m.marked_paths.clear();
+    m.mark_anchor = None;
+}
+
+// the entries that commands should act on: marked entries if any, else the one under the cursor
+fn selected_paths(m: &Model) -> Vec<PathBuf> {
+    if !m.marked_paths.is_empty() {
+        // in display order, including marked entries hidden by the filter
+        return m.sorted_entries
+            .iter()
+            .filter(|entry| is_marked(m, entry))
+            .map(|entry| entry.path.clone())
+            .collect();
+    }
+    match m.list_view.item_under_cursor() {
+        Some(entry) => vec![entry.path.clone()],
+        None => vec![],
+    }
+}
+
+// eg. to show an entry that was just created. does nothing if the filter hides it
+fn move_cursor_to_path(m: &mut Model, path: &Path) {
+    if let Some(index) = m.list_view.items.iter().position(|entry| entry.path == path) {
+        m.list_view.set_cursor(index);
+    }
+}
+
+// shared by every way of changing dirs
+fn goto_dir(m: &mut Model, pathbuf: PathBuf) {
+    change_history(m, HistoryChange::Visit(pathbuf.display().to_string(), Local::now()));
+    m.cwd_sort = SortBy::default();
+    m.cwd = pathbuf;
+    m.watcher = Watcher::start(&m.cwd, m.messages.clone());
+    m.recently_changed.clear();
+    m.entries_before_refresh = None;
+    load_cwd(m, true);
+    m.mode = Mode::Filter;
+    m.filter_text = "".to_string();
+    m.list_view.reset_with_items(m.sorted_entries.clone());
+    clear_marks(m);
+}
+
+fn palette_commands(m: &Model, mode: Mode) -> Vec<PaletteCommand> {
+    // keep the keys here in sync with key_action(). the tests check every one of them.
+    // only lists what makes sense in the given mode, eg. no up/down in the palette itself
+    macro_rules! command {
+        ($title:expr, $keys:expr, $action:expr) => {
+            PaletteCommand { title: $title.to_string(), keys: $keys.to_string(), action: $action }
+        };
+    }
+    let parent_dir = match m.cwd.parent() {
+        Some(path) => Action::GotoDir(path.to_owned()),
+        None => Action::Noop,
+    };
+    let path_under_cursor = m.jump_list.item_under_cursor().map(|candidate| candidate.path.clone());
+
+    match mode {
+        Mode::Filter => vec![
+            command!("clear filter", "Esc", Action::SetFilterText("".to_string())),
+            command!("open entry under cursor", "Enter", Action::SelectEntryUnderCursor),
+            command!("open entry under cursor with...", "Shift+W", Action::StartOpenWithMode),
+            command!("view file under cursor", "Shift+L", Action::StartPager),
+            command!("open a shell here", "Shift+T", Action::StartSubshell),
+            command!("go to parent dir", "Backspace (when filter is empty)", parent_dir),
+            command!("move cursor up", "Up, Shift+K", Action::TryCursorMoveUp),
+            command!("move cursor down", "Down, Shift+J", Action::TryCursorMoveDown),
+            command!("sort by name", "Shift+N", sort_action(m.cwd_sort, EntryAttribute::Name)),
+            command!("sort by size", "Shift+S", sort_action(m.cwd_sort, EntryAttribute::Size)),
+            command!("sort by date modified", "Shift+M", sort_action(m.cwd_sort, EntryAttribute::Date)),
+            command!("mark/unmark entry under cursor", "Tab", Action::ToggleMarkUnderCursor),
+            command!("mark range from last marked entry to cursor", "Shift+V", Action::MarkRangeToCursor),
+            command!("mark all", "Shift+A", Action::MarkAll),
+            command!("invert marks", "Shift+I", Action::InvertMarks),
+            command!("clear marks", "", Action::ClearMarks),
+            command!("copy marked entries (or entry under cursor)", "Shift+Y", Action::YankSelected),
+            command!("cut marked entries (or entry under cursor)", "Shift+X", Action::CutSelected),
+            command!("paste copied or cut entries here", "Ctrl+V", Action::Paste),
+            command!("move marked entries (or entry under cursor) to trash", "Delete", Action::TrashSelected),
+            command!("permanently delete marked entries (or entry under cursor)", "Shift+Delete", Action::DeleteSelectedPermanently),
+            command!("go to trash", "", Action::GotoTrash),
+            command!("restore marked entries (or entry under cursor) from trash", "", Action::RestoreSelectedFromTrash),
+            command!("rename marked entries (or all visible entries) in $EDITOR", "Shift+R", Action::BatchRename),
+            command!("undo last file operation", "Shift+U", Action::Undo),
+            command!("cancel the running file operation", "Shift+C", Action::CancelJob),
+            command!("new file", "", Action::StartNewFilePrompt),
+            command!("new folder", "", Action::StartNewDirPrompt),
+            command!("rename entry under cursor", "F2", Action::StartRenamePrompt),
+            command!("show/hide preview", "F3", Action::TogglePreview),
+            command!("jump to a frequent dir", "Shift+O", Action::StartJumpMode),
+            command!("switch to normal mode", "", Action::StartNormalMode),
+            command!("quit", "Shift+Q, Ctrl+C", Action::Quit),
+        ].into_iter().chain(custom_palette_commands(m)).collect(),
+        Mode::Normal => vec![
+            command!("filter entries", "/", Action::StartFilterMode),
+            command!("open entry under cursor", "Enter", Action::SelectEntryUnderCursor),
+            command!("open entry under cursor with...", "w", Action::StartOpenWithMode),
+            command!("view file under cursor", "L", Action::StartPager),
+            command!("open a shell here", "t", Action::StartSubshell),
+            command!("go to parent dir", "Backspace", parent_dir),
+            command!("move cursor up", "k, Up", Action::TryCursorMoveUp),
+            command!("move cursor down", "j, Down", Action::TryCursorMoveDown),
+            command!("sort by name", "n", sort_action(m.cwd_sort, EntryAttribute::Name)),
+            command!("sort by size", "s", sort_action(m.cwd_sort, EntryAttribute::Size)),
+            command!("sort by date modified", "m", sort_action(m.cwd_sort, EntryAttribute::Date)),
+            command!("mark/unmark entry under cursor", "Space", Action::ToggleMarkUnderCursor),
+            command!("mark range from last marked entry to cursor", "v", Action::MarkRangeToCursor),
+            command!("mark all", "a", Action::MarkAll),
+            command!("invert marks", "i", Action::InvertMarks),
+            command!("clear marks", "Esc", Action::ClearMarks),
+            command!("copy marked entries (or entry under cursor)", "y", Action::YankSelected),
+            command!("cut marked entries (or entry under cursor)", "x", Action::CutSelected),
+            command!("paste copied or cut entries here", "p", Action::Paste),
+            command!("move marked entries (or entry under cursor) to trash", "d, Delete", Action::TrashSelected),
+            command!("permanently delete marked entries (or entry under cursor)", "Shift+D", Action::DeleteSelectedPermanently),
+            command!("go to trash", "", Action::GotoTrash),
+            command!("restore marked entries (or entry under cursor) from trash", "", Action::RestoreSelectedFromTrash),
+            command!("rename marked entries (or all visible entries) in $EDITOR", "R", Action::BatchRename),
+            command!("undo last file operation", "u", Action::Undo),
+            command!("cancel the running file operation", "c", Action::CancelJob),
+            command!("new file", "", Action::StartNewFilePrompt),
+            command!("new folder", "", Action::StartNewDirPrompt),
+            command!("rename entry under cursor", "r, F2", Action::StartRenamePrompt),
+            command!("show/hide preview", "F3", Action::TogglePreview),
+            command!("jump to a frequent dir", ">", Action::StartJumpMode),
+            command!("quit", "q, Ctrl+C", Action::Quit),
+        ].into_iter().chain(custom_palette_commands(m)).collect(),
+        Mode::Jump => vec![
+            command!("go to dir under cursor", "Enter", match &path_under_cursor {
+                Some(path) => Action::JumpToDir(path.clone()),
+                None => Action::Noop,
+            }),
+            command!("move cursor up", "Up, Ctrl+K", Action::TryCursorMoveUp),
+            command!("move cursor down", "Down, Ctrl+J", Action::TryCursorMoveDown),
+            command!("clear jump text", "", Action::SetJumpText("".to_string())),
+            command!("edit jump list", "Ctrl+E", Action::StartEditHistoryMode),
+            command!("cancel jump", "Esc", Action::ExitJumpMode),
+            command!("quit", "Ctrl+C", Action::Quit),
+        ],
+        Mode::EditHistory => {
+            let mut commands = vec![
+                command!("move cursor up", "k, Up", Action::TryCursorMoveUp),
+                command!("move cursor down", "j, Down", Action::TryCursorMoveDown),
+                command!("back to jump list", "Esc, q", Action::ExitEditHistoryMode),
+                command!("quit", "Ctrl+C", Action::Quit),
+            ];
+            if let Some(path) = path_under_cursor {
+                commands.insert(0, command!("reset score of dir under cursor", "r", Action::ResetHistoryScore(path.clone())));
+                commands.insert(0, command!("pin/unpin dir under cursor", "p", Action::TogglePinInHistory(path.clone())));
+                commands.insert(0, command!("delete dir under cursor from history", "d", Action::RemoveFromHistory(path)));
+            }
+            commands
+        },
+        Mode::Pager => vec![
+            command!("scroll down", "j, Down, Enter", Action::PagerScrollDown(1)),
+            command!("scroll up", "k, Up", Action::PagerScrollUp(1)),
+            command!("page down", "Space, f, PageDown", Action::PagerScrollDown(m.rows - NUM_ROWS_OUTSIDE_LISTVIEW)),
+            command!("page up", "b, PageUp", Action::PagerScrollUp(m.rows - NUM_ROWS_OUTSIDE_LISTVIEW)),
+            command!("go to start", "g, Home", Action::PagerGotoStart),
+            command!("go to end", "Shift+G, End", Action::PagerGotoEnd),
+            command!("search", "/", Action::StartPagerSearchPrompt),
+            command!("next match", "n", Action::PagerSearchAgain { forward: true }),
+            command!("previous match", "Shift+N", Action::PagerSearchAgain { forward: false }),
+            command!("switch between text and hex", "x", Action::PagerToggleHex),
+            command!("close", "q, Esc", Action::ExitPager),
+        ],
+        Mode::CommandPalette | Mode::OpenWith => vec![],
+    }
+}
+
+fn custom_palette_commands(m: &Model) -> Vec<PaletteCommand> {
+    m.custom_commands
+        .iter()
+        .map(|command| PaletteCommand {
+            title: command.title.clone(),
+            keys: format!("${}", command.env_var),
+            action: Action::RunCustomCommand(command.clone()),
+        })
+        .collect()
+}
+
+fn sort_action(current_sort: SortBy, attribute: EntryAttribute) -> Action {
+    // pressing the same sort key twice reverses it
+    match current_sort.attribute == attribute {
+        true => Action::ReverseSort,
+        false => Action::ChangeSortOrder(attribute),
+    }
+}
+
+fn filter_palette_commands(commands: Vec<PaletteCommand>, query: &str) -> Vec<PaletteCommand> {
+    let mut scored_commands = commands
+        .into_iter()
+        .filter_map(|command| fuzzy_match_score(query, &command.title).map(|score| (score, command)))
+        .collect::<Vec<(i64, PaletteCommand)>>();
+    // stable sort, so ties keep their order from palette_commands()
+    scored_commands.sort_by(|(a, _), (b, _)| b.cmp(a));
+    scored_commands.into_iter().map(|(_, command)| command).collect()
+}
+
+fn filter_open_with_choices(choices: &[Opener], query: &str) -> Vec<Opener> {
+    let mut scored_choices = choices
+        .iter()
+        .filter_map(|opener| fuzzy_match_score(query, &opener.name).map(|score| (score, opener.clone())))
+        .collect::<Vec<(i64, Opener)>>();
+    // stable sort, so ties keep the best-guess order from open_with_choices()
+    scored_choices.sort_by(|(a, _), (b, _)| b.cmp(a));
+    scored_choices.into_iter().map(|(_, opener)| opener).collect()
+}
+
+fn fuzzy_match_score(query: &str, candidate: &str) -> Option<i64> {
+    // every char of the query has to appear in the candidate, in order, ignoring case and spaces.
+    // extra points for consecutive chars and for starts of words,
+    // so "sn" ranks "sort by name" above "move cursor down"
+    let candidate = candidate.to_lowercase().chars().collect::<Vec<char>>();
+    let mut score = 0;
+    let mut candidate_index = 0;
+    let mut previous_match : Option<usize> = None;
+
+    for query_char in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = candidate[candidate_index..].iter().position(|&c| c == query_char)?;
+        let match_index = candidate_index + found;
+
+        score += 1;
+        if match_index > 0 && previous_match == Some(match_index - 1) {
+            score += 5;
+        }
+        if match_index == 0 || !candidate[match_index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous_match = Some(match_index);
+        candidate_index = match_index + 1;
+    }
+    Some(score)
+}
+
+// --- VIEWS AND MESSY STRING HANDLING --- //
+
+fn view(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    // half-declarative view, without implementing a whole ui framework
+    // hinges on having only one flex span horiz and vert - rest are static sizes
+    //
+    // view must be impure function writing to mutable buf stderr
+    // since crossterm lib puts control bytes in custom types like SetBackgroundColor
+    // so view happens with mutable in queue!(buf, ...) function
+    // and not postponed for agnostic model/update/view loop
+    //
+    // maybe i can send a list of crossterm::Commands to queue...
+    // but probably not worth making a whole structure of dozens of commands, 
+    // only to delay writing to the same place one function later,
+    // just for the sake of 'purity'
+
+    // mockup:
+    //
+    //  C:\users\jkwon\desktop\programming\modenv
+    //  ___________________________________________
+    //   Name                v | Size   | Modified
+    //  ___________________________________________
+    //  loopy/                   12       2022-06
+    // *droopy/                  4        2022-06
+    // *grumpy/                  0        2022-06
+    //  frumpy/                  99       2022-06
+    // *script1.py               92 KB    2022-06
+    //  script_2.py              108 MB   2022-07
+    //  main.py                  1.2 GB   2022-05
+    //  utils.py                 985 B    2021-12
+    // 
+    //  (mode) :?!@>/someinputtext        item 2 of 20, and 3 selected
+    //  ___________________________________________
+    //
+    //
+    //  consider the sentence as a UI element, as proposed in the essay magic ink (i just read it)
+    //  also consider inspiration from other file manager status lines like:
+    //  https://raw.githubusercontent.com/ranger/ranger-assets/master/screenshots/multipane.png
+    
+    let divider : &str = &"-".repeat(m.cols);
+    let spacer : &str = &" ".repeat(m.cols);
+    macro_rules! divider {
+        () => {
+            queue!(stderr, Print(divider), MoveToNextLine(1))?;
+        };
+    }
+    #[allow(unused_macros)]
+    macro_rules! empty_line {
+        () => {
+            queue!(stderr, Print(spacer), MoveToNextLine(1))?;
+        };
+    }
+    queue!(stderr, crossterm::cursor::Hide)?;
+
+    view_cwd(m, stderr)?;            // height = 1 // 2
+    divider!();                     // height = 1
+    match m.mode {
+        Mode::Jump | Mode::EditHistory => {
+            view_jump_headers(m, stderr)?;   // height = 1
+            divider!();                     // height = 1
+            view_jump_list_body(m, stderr)?; // height = m.rows - 8
+        },
+        Mode::CommandPalette => {
+            view_palette_headers(m, stderr)?;   // height = 1
+            divider!();                        // height = 1
+            view_palette_list_body(m, stderr)?; // height = m.rows - 8
+        },
+        Mode::Pager => {
+            view_pager_headers(m, stderr)?;   // height = 1
+            divider!();                      // height = 1
+            view_pager_body(m, stderr)?;      // height = m.rows - 8
+        },
+        Mode::OpenWith => {
+            view_open_with_headers(m, stderr)?;   // height = 1
+            divider!();                          // height = 1
+            view_open_with_list_body(m, stderr)?; // height = m.rows - 8
+        },
+        _ => {
+            view_column_headers(m, stderr)?; // height = 1
+            divider!();                     // height = 1
+            view_list_body(m, stderr)?;      // height = m.rows - 8, with the preview to the right
+        },
+    };
+    // empty_line!();                  // height = 1
+    divider!();                     // height = 1
+    view_footer(m, stderr)?;         // height = 1
+    // empty_line!();                  // height = 1
+    Ok(())
+}
+
+fn view_cwd(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    queue!(stderr,
+           MoveTo(0,0),
+           // Print(" ".repeat(m.cols)), 
+           // MoveToNextLine(1),
+           fit(&format!(" {}", m.cwd.display()), m.cols),
+           MoveToNextLine(1)
+    )?;
+    Ok(())
+}
+
+fn view_column_headers(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    let cols = m.cols - preview_width(m);
+    let name_header = format!(" Name {}", sort_indicator(EntryAttribute::Name, m.cwd_sort));
+    let size_header = format!("Size {} ", sort_indicator(EntryAttribute::Size, m.cwd_sort));
+    let date_header = format!("  Modified {}  ", sort_indicator(EntryAttribute::Date, m.cwd_sort));
+    queue!(stderr, 
+           fit(&name_header, cols - SIZE_COLUMN_WIDTH - DATE_COLUMN_WIDTH - MARGIN_WIDTH),
+           Print(MARGIN),
+           fit(&size_header, SIZE_COLUMN_WIDTH),
+           fit(&date_header, DATE_COLUMN_WIDTH),
+    )?;
+    let title = m.list_view.item_under_cursor().map(|entry| entry.name.to_string()).unwrap_or_default();
+    view_preview_cell(m, stderr, &title)?;
+    queue!(stderr, MoveToNextLine(1))?;
+    Ok(())
+}
+
+fn view_list_body(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    // example of displaying list_view.items and indexes:
+    //
+    // all items indexes  
+    // on left
+    //                    
+    // out of   0 
+    // view     -----    viewable indexes
+    //          1   0    on right           
+    //          2   1           
+    //          3   2  
+    //          4   3
+    //          -----
+    // out of   5
+    // view
+    //
+    // first_viewable_index = 1
+    // max_items_visible = 4
+
+    let cols = m.cols - preview_width(m);
+    let viewable_entries = m.list_view.items.iter()
+        .skip(m.list_view.first_viewable_index)
+        .take(m.list_view.max_items_visible); 
+
+    let name_width = cols - SIZE_COLUMN_WIDTH - DATE_COLUMN_WIDTH - 2 * MARGIN_WIDTH;
+    let this_year = Local::now().year();
+    let mut row_cells = m.row_cells.borrow_mut();
+    // eg. after a resize, or on new year's
+    if row_cells.name_width != name_width || row_cells.this_year != this_year {
+        *row_cells = RowCells { name_width, this_year, rows: HashMap::new() };
+    }
+    // only the rows on screen now are kept for next time
+    let mut previous_rows = std::mem::take(&mut row_cells.rows);
+
+    for (visible_index, entry) in viewable_entries.enumerate() {
+        // reuses the key too, so a row that's still on screen doesn't clone its path every frame
+        let (path, cell) = match previous_rows.remove_entry(&entry.path) {
+            Some((path, cell)) if cell.is_made_from(entry) => (path, cell),
+            Some((path, _)) => (path, RowCell::new(entry, name_width, this_year)),
+            None => (entry.path.clone(), RowCell::new(entry, name_width, this_year)),
+        };
+
+        let at_cursor = m.list_view.cursor_index == visible_index + m.list_view.first_viewable_index;
+        let is_marked = is_marked(m, entry);
+        let recently_changed = m.recently_changed.contains_key(&entry.path);
+        if at_cursor { queue!(stderr, SetBackgroundColor(Color::DarkGrey))?; }
+        if is_marked { queue!(stderr, SetForegroundColor(Color::Yellow))?; }
+        else if recently_changed { queue!(stderr, SetForegroundColor(Color::Green))?; }
+
+        queue!(stderr,
+               Print(if is_marked { "*" } else { " " }),
+               Print(&cell.name_cell),
+               Print(MARGIN),
+               Print(&cell.size_cell),
+               Print(MARGIN),
+               Print(&cell.date_cell),
+        )?;
+        row_cells.rows.insert(path, cell);
+
+        if at_cursor || is_marked || recently_changed { queue!(stderr, ResetColor)?; }
+        view_preview_cell(m, stderr, preview_line(m, visible_index))?;
+        queue!(stderr, MoveToNextLine(1))?;
+    }
+
+    // draw over any empty rows
+    let num_visible = m.list_view.items.len().saturating_sub(m.list_view.first_viewable_index).min(m.list_view.max_items_visible);
+    for visible_index in num_visible..m.list_view.max_items_visible {
+        queue!(stderr, Print(" ".repeat(cols)))?;
+        view_preview_cell(m, stderr, preview_line(m, visible_index))?;
+        queue!(stderr, MoveToNextLine(1))?;
+    }
+    Ok(())
+}
+
+// the list always keeps enough room for its name, size and date columns
+const MIN_COLS_FOR_PREVIEW : usize = 80;
+
+// how much of the right side the preview takes, if any
+fn preview_width(m: &Model) -> usize {
+    match m.preview_visible && m.cols >= MIN_COLS_FOR_PREVIEW {
+        true => m.cols * 2 / 5,
+        false => 0,
+    }
+}
+
+// the preview's part of a header or list row, drawn at the end of it, so the preview lines up with the list
+fn view_preview_cell(m: &Model, stderr: &mut std::io::Stderr, text: &str) -> std::io::Result<()> {
+    let width = preview_width(m);
+    if width == 0 { return Ok(()); }
+    queue!(stderr, Print("|"), fit(&format!(" {}", text), width - 1))?;
+    Ok(())
+}
+
+// a line of the preview for the entry under the cursor, eg. the first line of a text file for row 0
+fn preview_line(m: &Model, row: usize) -> &str {
+    let Some(entry) = m.list_view.item_under_cursor() else { return ""; };
+    match m.previews.get(&entry.path) {
+        Some((preview, _)) => preview.lines().get(row).map_or("", String::as_str),
+        // not read yet
+        None if row == 0 => "loading...",
+        None => "",
+    }
+}
+
+fn view_jump_headers(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    queue!(stderr,
+           fit(" Jump to", m.cols - STATUS_COLUMN_WIDTH - SIZE_COLUMN_WIDTH - 2 * MARGIN_WIDTH),
+           Print(MARGIN),
+           fit("", STATUS_COLUMN_WIDTH),
+           Print(MARGIN),
+           fit("Score", SIZE_COLUMN_WIDTH),
+           MoveToNextLine(1)
+    )?;
+    Ok(())
+}
+
+fn view_jump_list_body(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    view_rows(&m.jump_list, m.cols, stderr, |candidate| {
+        let status = match (candidate.pinned, candidate.missing) {
+            (true, true) => "pinned, missing",
+            (true, false) => "pinned",
+            (false, true) => "missing",
+            (false, false) => "",
+        };
+        format!(" {}{}{}{}{} ",
+                fit_to_length(&candidate.path, m.cols - STATUS_COLUMN_WIDTH - SIZE_COLUMN_WIDTH - 3 * MARGIN_WIDTH),
+                MARGIN,
+                fit_to_length(status, STATUS_COLUMN_WIDTH),
+                MARGIN,
+                fit_to_length(&pad_align_right(&format!("{:.1}", candidate.score), SIZE_COLUMN_WIDTH), SIZE_COLUMN_WIDTH),
+        )
+    })?;
+    Ok(())
+}
+
+fn view_palette_headers(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    queue!(stderr,
+           fit(" Command", m.cols.saturating_sub(KEYS_COLUMN_WIDTH + MARGIN_WIDTH)),
+           Print(MARGIN),
+           fit("Keys", KEYS_COLUMN_WIDTH),
+           MoveToNextLine(1)
+    )?;
+    Ok(())
+}
+
+fn view_palette_list_body(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    view_rows(&m.palette_list, m.cols, stderr, |command| {
+        format!(" {}{}{} ",
+                fit_to_length(&command.title, m.cols.saturating_sub(KEYS_COLUMN_WIDTH + 2 * MARGIN_WIDTH)),
+                MARGIN,
+                fit_to_length(&command.keys, KEYS_COLUMN_WIDTH),
+        )
+    })?;
+    Ok(())
+}
+
+fn view_pager_headers(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    let Some(pager) = &m.pager else { return Ok(()); };
+    let position = format!("{}{}% of {} ", if pager.hex { "hex, " } else { "" }, pager.percent(), FileSize(pager.len).to_string().trim());
+    queue!(stderr,
+           fit(&format!(" {}", pager.path.display()), m.cols.saturating_sub(KEYS_COLUMN_WIDTH + MARGIN_WIDTH)),
+           Print(MARGIN),
+           fit(&pad_align_right(&position, KEYS_COLUMN_WIDTH), KEYS_COLUMN_WIDTH),
+           MoveToNextLine(1)
+    )?;
+    Ok(())
+}
+
+fn view_pager_body(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    let Some(pager) = &m.pager else { return Ok(()); };
+    for row in 0..m.rows - NUM_ROWS_OUTSIDE_LISTVIEW {
+        let line = fit_to_length(&format!(" {}", pager.lines.get(row).map_or("", String::as_str)), m.cols);
+        // highlight matches of the last search, like less does
+        let matches = match pager.query.is_empty() {
+            true => vec![],
+            false => line.match_indices(&pager.query).map(|(index, _)| index).collect::<Vec<usize>>(),
+        };
+        let mut printed = 0;
+        for index in matches {
+            queue!(stderr,
+                   Print(&line[printed..index]),
+                   SetBackgroundColor(Color::Yellow),
+                   SetForegroundColor(Color::Black),
+                   Print(&pager.query),
+                   ResetColor,
+            )?;
+            printed = index + pager.query.len();
+        }
+        queue!(stderr, Print(&line[printed..]), MoveToNextLine(1))?;
+    }
+    Ok(())
+}
+
+fn view_open_with_headers(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    let filename = m.open_with_path.file_name().unwrap_or_default().to_string_lossy();
+    queue!(stderr,
+           fit(&format!(" Open {} with", filename), m.cols.saturating_sub(KEYS_COLUMN_WIDTH + MARGIN_WIDTH)),
+           Print(MARGIN),
+           fit("Command", KEYS_COLUMN_WIDTH),
+           MoveToNextLine(1)
+    )?;
+    Ok(())
+}
+
+fn view_open_with_list_body(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    view_rows(&m.open_with_list, m.cols, stderr, |opener| {
+        let command = match opener.in_terminal {
+            true => format!("{} (in terminal)", opener.command),
+            false => opener.command.clone(),
+        };
+        format!(" {}{}{} ",
+                fit_to_length(&opener.name, m.cols.saturating_sub(KEYS_COLUMN_WIDTH + 2 * MARGIN_WIDTH)),
+                MARGIN,
+                fit_to_length(&command, KEYS_COLUMN_WIDTH),
+        )
+    })?;
+    Ok(())
+}
+
+// draws the visible slice of any list, highlighting the row under the cursor.
+// format_row should return a string exactly cols wide
+fn view_rows<T>(list: &ListViewData<T>, cols: usize, stderr: &mut std::io::Stderr, format_row: impl Fn(&T) -> String) -> std::io::Result<()> {
+    let viewable_items = list.items.iter()
+        .skip(list.first_viewable_index)
+        .take(list.max_items_visible);
+
+    for (visible_index, item) in viewable_items.enumerate() {
+        let at_cursor = list.cursor_index == visible_index + list.first_viewable_index;
+        if at_cursor { queue!(stderr, SetBackgroundColor(Color::DarkGrey))?; }
+
+        queue!(stderr, Print(format_row(item)), MoveToNextLine(1))?;
+
+        if at_cursor { queue!(stderr, ResetColor)?; }
+    }
+
+    // draw over any empty rows
+    if list.max_items_visible > list.items.len() {
+        let empty_rows = list.max_items_visible - list.items.len();
+
+        for _ in 0..empty_rows {
+            queue!(stderr, Print(" ".repeat(cols)), MoveToNextLine(1))?;
+        }
+    }
+    Ok(())
+}
+
+fn view_footer(m: &Model, stderr: &mut std::io::Stderr) -> std::io::Result<()> {
+    let mut input_field = format!(" {} {}",
+        match m.mode {
+            Mode::Filter => "(filter)",
+            Mode::Normal => "(normal)",
+            Mode::Jump => "(jump)",
+            Mode::EditHistory => "(edit history)",
+            Mode::CommandPalette => "(palette)",
+            Mode::OpenWith => "(open with)",
+            Mode::Pager => "(pager)",
+        },
+        match m.mode {
+            Mode::Filter => format!(" /{}", m.filter_text),
+            Mode::Jump => format!(" >{}", m.jump_text),
+            Mode::EditHistory => " d: delete, p: pin, r: reset score, Esc: back".to_string(),
+            Mode::CommandPalette => format!(" :{}", m.palette_text),
+            Mode::OpenWith => format!(" :{}", m.open_with_text),
+            Mode::Pager => " /: search, n/N: next/previous, x: hex, q: close".to_string(),
+            _ => String::new(),
+        },
+    );
+    if let Some(confirmation) = &m.pending_confirmation {
+        input_field = format!(" {} [y/n] ", confirmation.prompt);
+    }
+    if let Some(prompt) = &m.prompt {
+        input_field = format!(" {} {}", prompt.label, prompt.text);
+    }
+    // eg. "copying 3 entries: 1.2 GB of 4.0 GB, 12 of 40 files, 1m 20s left"
+    let job_progress = match &m.running_job {
+        Some(job) => describe_progress(job),
+        None => String::new(),
+    };
+    // eg. "item 2 of 20, 3 marked, 1 to paste"
+    let position = match m.mode {
+        Mode::Filter | Mode::Normal => {
+            let mut position = format!("item {} of {}", (m.list_view.cursor_index + 1).min(m.list_view.items.len()), m.list_view.items.len());
+            if !m.marked_paths.is_empty() {
+                position = format!("{}, {} marked", position, m.marked_paths.len());
+            }
+            if let Some(register) = &m.yank_register {
+                position = format!("{}, {} to paste", position, register.paths.len());
+            }
+            position
+        },
+        _ => String::new(),
+    };
+    // while typing in a prompt, say what's wrong with the text so far
+    let validation_error = match &m.prompt {
+        Some(prompt) => prompt.problem.clone().unwrap_or_default(),
+        None => String::new(),
+    };
+    let position = match m.prompt {
+        Some(_) => validation_error,
+        None => position,
+    };
+    let status = [m.status_message.as_str(), job_progress.as_str(), position.as_str()]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .cloned()
+        .collect::<Vec<&str>>()
+        .join(MARGIN);
+
+    // right aligned, in whatever space the input field leaves
+    let status = fit_to_length(&status, m.cols.saturating_sub(str_length(&input_field) + 2 * MARGIN_WIDTH));
+    let status = status.trim_end();
+
+    queue!(stderr, 
+           // clear any artifacts from previous draw
+           Print(" ".repeat(m.cols)),
+           MoveToColumn((m.cols - str_length(status)).try_into().unwrap_or(1)),
+           Print(status),
+           // display filter field last, so the cursor ends up where text gets typed
+           MoveToColumn(1),
+           Print(&input_field),
+    )?;
+    match (m.mode, m.pending_confirmation.is_some() || m.prompt.is_some()) {
+        (_, true) | (Mode::Filter | Mode::Jump | Mode::CommandPalette | Mode::OpenWith, _) => queue!(stderr, crossterm::cursor::Show,)?,
+        _ => queue!(stderr, crossterm::cursor::Hide,)?,
+    };
+    Ok(())
+}
+
+// --- view helpers --- //
+
+fn describe_progress(job: &RunningJob) -> String {
+    use std::sync::atomic::Ordering;
+    let total_files = job.progress.total_files.load(Ordering::Relaxed);
+    let total_bytes = job.progress.total_bytes.load(Ordering::Relaxed);
+    if total_files == 0 && total_bytes == 0 {
+        return format!("{}: counting...", job.description);
+    }
+    let mut progress = format!("{}: {} of {}, {} of {} files",
+        job.description,
+        FileSize(job.progress.bytes_done.load(Ordering::Relaxed)).to_string().trim(),
+        FileSize(total_bytes).to_string().trim(),
+        job.progress.files_done.load(Ordering::Relaxed),
+        total_files,
+    );
+    if let Some(time_left) = job.estimate_time_left() {
+        progress = format!("{}, {} left", progress, describe_duration(time_left.as_secs()));
+    }
+    progress
+}
+
+// eg. "1h 5m", "2m 30s", "12s"
+fn describe_duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+fn sort_indicator(match_attribute: EntryAttribute, current_sort: SortBy) -> &'static str {
+    if match_attribute != current_sort.attribute { return " "; }
+
+    match current_sort.ascending {
+        true => "^",
+        false => "v",
+    }
+}
+
+fn fit(s: &str, final_length: usize) -> Print<String> {
+    Print(fit_to_length(s, final_length))
+}
+
+fn fit_to_length(s: &str, final_length: usize) -> String {
+    // bad: assuming only ASCII input...
+    match str_length(s) {
+        // too short
+        length if length <= final_length => {
+            pad_align_left(s, final_length)
+        },
+        // too long
+        length => {
+            s.chars().take(final_length).collect::<String>()
+        }
+    }
+}
+
+fn str_length<S: AsRef<str>> (s: S) -> usize {
+    // bad: assuming only ASCII input...
+    s.as_ref().chars().count()
+}
+
+fn pad_align_left(s: &str, final_length: usize) -> String {
+    // pad string$0 with : spaces, left aligned <, to meet final_length$1
+    format!("{0: <1$}", s, final_length) 
+}
+
+fn pad_align_right(s: &str, final_length: usize) -> String {
+    format!("{0: >1$}", s, final_length) 
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a model in a dir of its own, with its own history and config
+    fn test_model(name: &str) -> Model {
+        let dir = std::env::temp_dir().join(format!("fmin_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("cwd/sub")).unwrap();
+        let (messages, _) = std::sync::mpsc::channel();
+        new_model(dir.join("cwd"), 100, 30, dir.join("data"), messages)
+    }
+
+    // the key a palette label names, as the terminal would send it. eg. "Shift+K", "Ctrl+E", "R" or "F2"
+    fn parse_key(label: &str) -> KeyEvent {
+        let (modifiers, key) = match label.split_once('+') {
+            Some(("Shift", key)) => (KeyModifiers::SHIFT, key),
+            Some(("Ctrl", key)) => (KeyModifiers::CONTROL, key),
+            _ => (KeyModifiers::NONE, label),
+        };
+        let code = match key {
+            "Esc" => KeyCode::Esc,
+            "Enter" => KeyCode::Enter,
+            "Backspace" => KeyCode::Backspace,
+            "Tab" => KeyCode::Tab,
+            "Delete" => KeyCode::Delete,
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            "Space" => KeyCode::Char(' '),
+            _ if key.len() > 1 && key.starts_with('F') => KeyCode::F(key[1..].parse().unwrap()),
+            _ => {
+                let mut chars = key.chars();
+                let c = chars.next().unwrap();
+                assert!(chars.next().is_none(), "unknown key: {}", label);
+                KeyCode::Char(c)
+            },
+        };
+        // ctrl+letters come lowercase, and capital letters come with shift, whether the label says so or not
+        match (code, modifiers) {
+            (KeyCode::Char(c), KeyModifiers::CONTROL) => KeyEvent::new(KeyCode::Char(c.to_ascii_lowercase()), modifiers),
+            (KeyCode::Char(c), _) if c.is_ascii_uppercase() => KeyEvent::new(code, KeyModifiers::SHIFT),
+            _ => KeyEvent::new(code, modifiers),
+        }
+    }
+
+    #[test]
+    fn palette_keys_do_what_the_palette_says() {
+        let mut m = test_model("palette_keys");
+        change_history(&mut m, HistoryChange::Visit("/tmp".to_string(), Local::now()));
+        for mode in [Mode::Filter, Mode::Normal, Mode::Jump, Mode::EditHistory, Mode::Pager] {
+            m.mode = mode;
+            m.jump_list.reset_with_items(rank_jump_list(&m));
+            for command in palette_commands(&m, mode) {
+                // eg. "Backspace (when filter is empty)", which it is here
+                let keys = command.keys.split(" (").next().unwrap_or_default();
+                for label in keys.split(", ").filter(|label| !label.is_empty()) {
+                    // handled before key_action(), in every mode
+                    if label == "Ctrl+C" || label.starts_with('$') { continue; }
+                    let action = key_action(&m, Event::Key(parse_key(label)));
+                    assert_eq!(action, command.action, "{} in {:?} mode, for \"{}\"", label, mode, command.title);
+                }
+            }
+        }
+        let _ = std::fs::remove_dir_all(m.data_dir.parent().unwrap());
+    }
+
+    #[test]
+    fn jumping_to_a_missing_dir_stays_put() {
+        let mut m = test_model("jump_missing");
+        let gone = m.data_dir.parent().unwrap().join("gone");
+        std::fs::create_dir(&gone).unwrap();
+        change_history(&mut m, HistoryChange::Visit(gone.display().to_string(), Local::now()));
+        apply_action(&mut m, Action::StartJumpMode);
+        // removed after jump mode found it, so the list doesn't know yet
+        std::fs::remove_dir(&gone).unwrap();
+        let action = key_action(&m, Event::Key(parse_key("Enter")));
+        assert_eq!(action, Action::JumpToDir(gone.display().to_string()));
+        apply_action(&mut m, action);
+        assert_eq!(m.cwd, m.data_dir.parent().unwrap().join("cwd"));
+        assert_eq!(m.mode, Mode::Jump);
+        assert!(m.status_message.contains("is missing"), "{}", m.status_message);
+        assert!(m.jump_list.item_under_cursor().unwrap().missing);
+        let _ = std::fs::remove_dir_all(m.data_dir.parent().unwrap());
+    }
+
+    fn history_test_file(name: &str, contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fmin_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let filepath = dir.join(".fmin_history");
+        std::fs::write(&filepath, contents).unwrap();
+        filepath
+    }
+
+    #[test]
+    fn history_files_of_every_version_read_and_round_trip() {
+        let date = Local.timestamp_opt(1700000000, 0).unwrap();
+        let data = |frequency, last_visit, sort: Option<char>, pinned| HistoryData {
+            frequency,
+            last_visit,
+            sort: sort.and_then(SortBy::from_char),
+            pinned,
+        };
+        let samples = [
+            // version 1 has no header, and commas in paths are fine in every version
+            ("v1", "12,/a/b\n3,/with,comma\n", vec![
+                ("/a/b", data(12, None, None, false)),
+                ("/with,comma", data(3, None, None, false)),
+            ]),
+            ("v2", "#fmin_history 2\nS,1700000000,4,/x\n-,-,1,/y\n", vec![
+                ("/x", data(4, Some(date), Some('S'), false)),
+                ("/y", data(1, None, None, false)),
+            ]),
+            // the bad lines are skipped, not the whole file
+            ("v3", "#fmin_history 3\np,m,1700000000,7,/z\n-,-,-,2,/w\nx,-,-,2,/bad pin\n-,-,-,lots,/bad count\n", vec![
+                ("/z", data(7, Some(date), Some('m'), true)),
+                ("/w", data(2, None, None, false)),
+            ]),
+        ];
+        for (name, contents, expected) in samples {
+            let filepath = history_test_file(&format!("history_{}", name), contents);
+            let expected: HashMap<HistoryPath, HistoryData> = expected.into_iter().map(|(path, data)| (path.to_string(), data)).collect();
+            let history = read_history_file(&filepath).unwrap();
+            assert_eq!(history, expected, "reading {}", name);
+
+            // older versions are migrated to the current one on write
+            write_history_file(&history, &filepath).unwrap();
+            let written = std::fs::read_to_string(&filepath).unwrap();
+            assert!(written.starts_with(&format!("{} {}\n", HISTORY_HEADER, HISTORY_VERSION)), "{}", written);
+            assert_eq!(read_history_file(&filepath).unwrap(), expected, "rereading {}", name);
+            let _ = std::fs::remove_dir_all(filepath.parent().unwrap());
+        }
+    }
+
+    #[test]
+    fn history_files_from_a_newer_fmin_are_refused() {
+        let filepath = history_test_file("history_newer", &format!("{} {}\n?,?,1,/x\n", HISTORY_HEADER, HISTORY_VERSION + 1));
+        assert!(read_history_file(&filepath).is_err());
+        assert!(is_newer_history_file(&filepath));
+        let _ = std::fs::remove_dir_all(filepath.parent().unwrap());
+    }
+
+    #[test]
+    fn sessions_saving_history_at_once_keep_each_others_visits() {
+        let mut first = test_model("history_sessions");
+        let dir = first.data_dir.parent().unwrap().to_path_buf();
+        // started while the first is still open, so neither sees the other's visits in memory
+        let (messages, _) = std::sync::mpsc::channel();
+        let mut second = new_model(dir.join("cwd"), 100, 30, dir.join("data"), messages);
+        change_history(&mut first, HistoryChange::Visit("/first".to_string(), Local::now()));
+        change_history(&mut second, HistoryChange::Visit("/second".to_string(), Local::now()));
+        change_history(&mut second, HistoryChange::Pin("/second".to_string(), true));
+
+        save_history(&first.history, &first.history_changes, &first.history_filepath).unwrap();
+        save_history(&second.history, &second.history_changes, &second.history_filepath).unwrap();
+        let history = read_history_file(&first.history_filepath).unwrap();
+        assert_eq!(history["/first"].frequency, 1);
+        assert!(history["/second"].pinned);
+        // both sessions started there
+        assert_eq!(history[&first.cwd.display().to_string()].frequency, 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn saving_history_falls_back_to_the_session_when_the_file_cant_be_reread() {
+        let mut m = test_model("history_fallback");
+        change_history(&mut m, HistoryChange::Visit("/visited".to_string(), Local::now()));
+        // not utf-8, so reading it fails partway
+        std::fs::create_dir_all(&m.data_dir).unwrap();
+        std::fs::write(&m.history_filepath, b"#fmin_history 3\n-,-,-,5,/\xff\n").unwrap();
+        save_history(&m.history, &m.history_changes, &m.history_filepath).unwrap();
+        // the file only keeps whole seconds
+        let history = read_history_file(&m.history_filepath).unwrap();
+        let frequencies = |history: &HashMap<HistoryPath, HistoryData>| history.iter().map(|(path, data)| (path.clone(), data.frequency)).collect::<Vec<_>>();
+        let (mut read, mut expected) = (frequencies(&history), frequencies(&m.history));
+        read.sort();
+        expected.sort();
+        assert_eq!(read, expected);
+
+        // but a file from a newer fmin is left alone
+        let newer = format!("{} {}\n?,?,1,/x\n", HISTORY_HEADER, HISTORY_VERSION + 1);
+        std::fs::write(&m.history_filepath, &newer).unwrap();
+        assert!(save_history(&m.history, &m.history_changes, &m.history_filepath).is_err());
+        assert_eq!(std::fs::read_to_string(&m.history_filepath).unwrap(), newer);
+        let _ = std::fs::remove_dir_all(m.data_dir.parent().unwrap());
+    }
+}
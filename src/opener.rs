@@ -0,0 +1,419 @@
+// mini file opener: picks a program for a file, then runs it in the background or in the terminal.
+// also builds the open-with list from the programs installed on the system, like file managers do:
+// https://specifications.freedesktop.org/desktop-entry-spec/latest/
+// https://specifications.freedesktop.org/mime-apps-spec/latest/
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::{Config, Opener, OpenRule};
+use crate::write_file_atomically;
+
+// picks from the config first, then $FMIN_OPEN, then the system's default opener
+pub fn choose_opener(rules: &[OpenRule], path: &Path) -> Opener {
+    let filename = path.file_name().unwrap_or_default().to_string_lossy();
+    if let Some(rule) = rules.iter().find(|rule| glob_match(&rule.pattern, &filename)) {
+        return rule.opener.clone();
+    }
+    env_opener().unwrap_or_else(system_opener)
+}
+
+fn env_opener() -> Option<Opener> {
+    // could be anything, like a script that opens vim, so give it the terminal to be safe
+    std::env::var("FMIN_OPEN").ok().map(|command| Opener { name: command.clone(), command, in_terminal: true })
+}
+
+fn system_opener() -> Opener {
+    Opener { name: "default app".to_string(), command: SYSTEM_OPENER.to_string(), in_terminal: false }
+}
+
+#[cfg(target_os = "macos")]
+const SYSTEM_OPENER : &str = "open";
+#[cfg(not(target_os = "macos"))]
+const SYSTEM_OPENER : &str = "xdg-open";
+
+pub enum Opening {
+    // started in the background, with a message for the status line
+    Detached(String),
+    // needs the terminal, which only the tui program can hand over
+    InTerminal(Command),
+}
+
+pub fn open(opener: &Opener, path: &Path, cwd: &Path) -> Opening {
+    let filename = path.file_name().unwrap_or_default().to_string_lossy();
+    // the path goes in as "$1", so it never needs quoting, and {} can put it somewhere else in the command
+    let script = match opener.command.contains("{}") {
+        true => opener.command.replace("{}", "\"$1\""),
+        false => format!("{} \"$1\"", opener.command),
+    };
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script).arg("fmin").arg(path).current_dir(cwd);
+
+    if opener.in_terminal {
+        return Opening::InTerminal(command);
+    }
+
+    // detached, so it can't draw over the tui or read its keypresses,
+    // and keeps running after fmin quits
+    command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // its own process group, so ctrl+c in the terminal doesn't reach it
+        command.process_group(0);
+    }
+    let message = match command.spawn() {
+        Ok(mut child) => {
+            // reap it whenever it exits, so it doesn't hang around as a zombie
+            std::thread::spawn(move || child.wait());
+            format!("opened {} with {}", filename, opener.name)
+        },
+        Err(err) => format!("couldn't run {}: {}", opener.command, err),
+    };
+    Opening::Detached(message)
+}
+
+// shell-style wildcards, where * matches any run of chars and ? matches one.
+// ignores case, so *.jpg matches PHOTO.JPG too
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<char>>();
+    let name = name.to_lowercase().chars().collect::<Vec<char>>();
+    // classic backtracking to the last *, which only ever needs to remember one
+    let (mut p, mut n) = (0, 0);
+    let mut last_star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                last_star = Some((p, n));
+                p += 1;
+            },
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            },
+            _ => match last_star {
+                // let the star eat one more char and try again
+                Some((star_p, star_n)) => {
+                    last_star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                },
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// --- open with --- //
+
+// everything that could open the file, best guesses first:
+// the last choice for this extension, then the config, then installed apps, then the default
+pub fn open_with_choices(config: &Config, path: &Path, last_choice: Option<&String>) -> Vec<Opener> {
+    let filename = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut choices = config.open_rules
+        .iter()
+        .filter(|rule| glob_match(&rule.pattern, &filename))
+        .map(|rule| rule.opener.clone())
+        .collect::<Vec<Opener>>();
+    choices.extend(config.open_with.iter().cloned());
+    choices.extend(installed_applications(&detect_mime_types(path)));
+    choices.extend(env_opener());
+    choices.push(system_opener());
+
+    // the same app can be installed twice, eg. by the system and by flatpak
+    let mut seen = HashSet::new();
+    choices.retain(|opener| seen.insert((opener.name.clone(), opener.command.clone())));
+
+    if let Some(index) = choices.iter().position(|opener| Some(&opener.name) == last_choice) {
+        let opener = choices.remove(index);
+        choices.insert(0, opener);
+    }
+    choices
+}
+
+// the last open-with choice for each extension, from a file like
+// pdf,Firefox
+// md,vim
+pub fn read_last_choices(filepath: &Path) -> HashMap<String, String> {
+    let contents = std::fs::read_to_string(filepath).unwrap_or_default();
+    contents
+        .lines()
+        .filter_map(|line| line.split_once(','))
+        .map(|(extension, name)| (extension.to_string(), name.to_string()))
+        .collect()
+}
+
+pub fn remember_choice(filepath: &Path, path: &Path, name: &str) -> io::Result<()> {
+    let Some(extension) = choice_key(path) else { return Ok(()); };
+    let mut last_choices = read_last_choices(filepath);
+    last_choices.insert(extension, name.to_string());
+    let mut lines = last_choices
+        .iter()
+        .map(|(extension, name)| format!("{},{}\n", extension, name))
+        .collect::<Vec<String>>();
+    lines.sort();
+    write_file_atomically(filepath, lines.concat().as_bytes())
+}
+
+// eg. Notes.PDF -> pdf. files without an extension don't get remembered,
+// since Makefile and a random binary have nothing in common
+pub fn choice_key(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    match extension.contains(',') || extension.contains('\n') {
+        true => None,
+        false => Some(extension),
+    }
+}
+
+// --- mime types --- //
+
+// eg. ["text/markdown", "text/plain"], most specific first,
+// so apps that claim a parent type like text/plain show up too.
+// magic bytes go first, since an extension can lie, except for formats like zip and plain text
+// that other formats are built on, where the extension knows better, eg. .docx or .rs
+pub fn detect_mime_types(path: &Path) -> Vec<String> {
+    if path.is_dir() {
+        return vec!["inode/directory".to_string()];
+    }
+    let mut head = Vec::new();
+    if let Ok(file) = File::open(path) {
+        if file.take(512).read_to_end(&mut head).is_err() {
+            head.clear();
+        }
+    }
+    let by_content = sniff_mime_type(&head);
+    let by_extension = path.extension().and_then(|extension| mime_type_for_extension(&extension.to_string_lossy().to_lowercase()));
+
+    let mime_type = match (by_content, by_extension) {
+        (Some(by_content), _) if by_content != "text/plain" && by_content != "application/zip" => by_content.to_string(),
+        (_, Some(by_extension)) => by_extension,
+        (Some(by_content), None) => by_content.to_string(),
+        (None, None) => "application/octet-stream".to_string(),
+    };
+
+    let mut mime_types = vec![mime_type.clone()];
+    let is_text = by_content == Some("text/plain")
+        || mime_type.starts_with("text/")
+        || mime_type.ends_with("+xml")
+        || mime_type.ends_with("+json");
+    if is_text && mime_type != "text/plain" {
+        mime_types.push("text/plain".to_string());
+    }
+    // anything can be opened as raw bytes, eg. by a hex editor
+    if mime_type != "application/octet-stream" && mime_type != "inode/directory" {
+        mime_types.push("application/octet-stream".to_string());
+    }
+    mime_types
+}
+
+// https://en.wikipedia.org/wiki/List_of_file_signatures
+fn sniff_mime_type(head: &[u8]) -> Option<&'static str> {
+    const SIGNATURES : &[(usize, &[u8], &str)] = &[
+        (0, b"%PDF-", "application/pdf"),
+        (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+        (0, b"\xff\xd8\xff", "image/jpeg"),
+        (0, b"GIF87a", "image/gif"),
+        (0, b"GIF89a", "image/gif"),
+        (8, b"WEBP", "image/webp"),
+        (0, b"II*\x00", "image/tiff"),
+        (0, b"MM\x00*", "image/tiff"),
+        (0, b"PK\x03\x04", "application/zip"),
+        (0, b"\x1f\x8b", "application/gzip"),
+        (0, b"BZh", "application/x-bzip2"),
+        (0, b"\xfd7zXZ\x00", "application/x-xz"),
+        (0, b"(\xb5/\xfd", "application/zstd"),
+        (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+        (0, b"Rar!\x1a\x07", "application/vnd.rar"),
+        (257, b"ustar", "application/x-tar"),
+        (0, b"\x7fELF", "application/x-executable"),
+        (0, b"SQLite format 3\x00", "application/vnd.sqlite3"),
+        (0, b"%!PS", "application/postscript"),
+        (0, b"ID3", "audio/mpeg"),
+        (0, b"\xff\xfb", "audio/mpeg"),
+        (0, b"fLaC", "audio/flac"),
+        (0, b"OggS", "audio/ogg"),
+        (8, b"WAVE", "audio/x-wav"),
+        (4, b"ftyp", "video/mp4"),
+        (0, b"\x1a\x45\xdf\xa3", "video/x-matroska"),
+        (8, b"AVI ", "video/x-msvideo"),
+        (0, b"#!", "application/x-shellscript"),
+    ];
+    let signature = SIGNATURES
+        .iter()
+        .find(|(offset, magic, _)| head.get(*offset..offset + magic.len()) == Some(*magic));
+    if let Some((_, _, mime_type)) = signature {
+        return Some(mime_type);
+    }
+    // looks like text if it's utf-8 without NULs. the 512 bytes may end halfway through a char
+    let is_utf8 = match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none(),
+    };
+    match is_utf8 && !head.contains(&0) {
+        true => Some("text/plain"),
+        false => None,
+    }
+}
+
+// from /etc/mime.types when there is one, which lists lines like
+// application/pdf					pdf
+// else from a short list of the usual suspects
+fn mime_type_for_extension(extension: &str) -> Option<String> {
+    if let Ok(contents) = std::fs::read_to_string("/etc/mime.types") {
+        let mime_type = contents
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .find_map(|line| {
+                let mut words = line.split_whitespace();
+                let mime_type = words.next()?;
+                words.any(|word| word == extension).then(|| mime_type.to_string())
+            });
+        if mime_type.is_some() {
+            return mime_type;
+        }
+    }
+    const EXTENSIONS : &[(&str, &str)] = &[
+        ("txt", "text/plain"), ("md", "text/markdown"), ("html", "text/html"), ("htm", "text/html"),
+        ("css", "text/css"), ("csv", "text/csv"), ("js", "text/javascript"), ("json", "application/json"),
+        ("xml", "application/xml"), ("svg", "image/svg+xml"), ("rs", "text/rust"), ("py", "text/x-python"),
+        ("c", "text/x-csrc"), ("h", "text/x-chdr"), ("sh", "application/x-shellscript"), ("toml", "application/toml"),
+        ("pdf", "application/pdf"), ("png", "image/png"), ("jpg", "image/jpeg"), ("jpeg", "image/jpeg"),
+        ("gif", "image/gif"), ("webp", "image/webp"), ("mp3", "audio/mpeg"), ("flac", "audio/flac"),
+        ("ogg", "audio/ogg"), ("wav", "audio/x-wav"), ("mp4", "video/mp4"), ("mkv", "video/x-matroska"),
+        ("webm", "video/webm"), ("zip", "application/zip"), ("gz", "application/gzip"), ("tar", "application/x-tar"),
+        ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+        ("odt", "application/vnd.oasis.opendocument.text"), ("epub", "application/epub+zip"),
+    ];
+    EXTENSIONS
+        .iter()
+        .find(|(known_extension, _)| *known_extension == extension)
+        .map(|(_, mime_type)| mime_type.to_string())
+}
+
+// --- .desktop files --- //
+
+struct DesktopEntry {
+    name: String,
+    exec: String,
+    terminal: bool,
+    mime_types: Vec<String>,
+}
+
+// apps from .desktop files that claim one of the mime types, sorted by how specific the match is, then by name
+fn installed_applications(mime_types: &[String]) -> Vec<Opener> {
+    let mut seen_ids = HashSet::new();
+    let mut applications = Vec::new();
+    for dir in application_dirs() {
+        for (desktop_id, path) in find_desktop_files(&dir) {
+            // an app in ~/.local/share hides the system one with the same id, eg. to change its Exec
+            if !seen_ids.insert(desktop_id) { continue; }
+            let Some(entry) = read_desktop_entry(&path) else { continue; };
+            // desktop files may use wildcards like image/*
+            let rank = mime_types
+                .iter()
+                .position(|mime_type| entry.mime_types.iter().any(|claimed| glob_match(claimed, mime_type)));
+            if let Some(rank) = rank {
+                let opener = Opener { name: entry.name, command: exec_to_command(&entry.exec), in_terminal: entry.terminal };
+                applications.push((rank, opener));
+            }
+        }
+    }
+    applications.sort_by_key(|(rank, opener)| (*rank, opener.name.to_lowercase()));
+    applications.into_iter().map(|(_, opener)| opener).collect()
+}
+
+// $XDG_DATA_HOME first, since the user's own apps win over the system's
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = match (std::env::var("XDG_DATA_HOME"), std::env::var("HOME")) {
+        (Ok(data_home), _) if !data_home.is_empty() => Some(PathBuf::from(data_home)),
+        (_, Ok(home)) => Some(PathBuf::from(home).join(".local/share")),
+        _ => None,
+    };
+    let data_dirs = match std::env::var("XDG_DATA_DIRS") {
+        Ok(data_dirs) if !data_dirs.is_empty() => data_dirs,
+        _ => "/usr/local/share:/usr/share".to_string(),
+    };
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from))
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+// (desktop id, path) pairs, where files in subdirs get ids like kde-okular.desktop
+fn find_desktop_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut desktop_files = Vec::new();
+    let mut unvisited = vec![dir.to_path_buf()];
+    while let Some(path) = unvisited.pop() {
+        let Ok(dir_entries) = std::fs::read_dir(&path) else { continue; };
+        for dir_entry in dir_entries.filter_map(|dir_entry| dir_entry.ok()) {
+            let path = dir_entry.path();
+            if path.is_dir() {
+                unvisited.push(path);
+            } else if path.extension().map_or(false, |extension| extension == "desktop") {
+                let desktop_id = path.strip_prefix(dir).unwrap_or(&path).to_string_lossy().replace('/', "-");
+                desktop_files.push((desktop_id, path));
+            }
+        }
+    }
+    desktop_files
+}
+
+// only reads the keys needed to open files, from the [Desktop Entry] group.
+// hidden entries count as uninstalled, but NoDisplay ones can still open files, they're just not in menus
+fn read_desktop_entry(path: &Path) -> Option<DesktopEntry> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut in_main_group = false;
+    let (mut name, mut exec) = (None, None);
+    let (mut terminal, mut is_application, mut hidden) = (false, false, false);
+    let mut mime_types = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_main_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_main_group { continue; }
+        let Some((key, value)) = line.split_once('=') else { continue; };
+        // translated keys like Name[de] are skipped, since the plain one is always there too
+        match (key.trim(), value.trim()) {
+            ("Name", value) => name = Some(value.to_string()),
+            ("Exec", value) => exec = Some(value.to_string()),
+            ("Terminal", value) => terminal = value == "true",
+            ("Type", value) => is_application = value == "Application",
+            ("Hidden", value) => hidden = value == "true",
+            ("MimeType", value) => mime_types = value.split(';').filter(|mime_type| !mime_type.is_empty()).map(String::from).collect(),
+            _ => (),
+        }
+    }
+    if !is_application || hidden { return None; }
+    Some(DesktopEntry { name: name?, exec: exec?, terminal, mime_types })
+}
+
+// eg. "firefox %u" -> "firefox {}". the first file code takes the path and any others are dropped,
+// along with codes like %i for the icon, which only make sense for launchers
+// https://specifications.freedesktop.org/desktop-entry-spec/latest/exec-variables.html
+fn exec_to_command(exec: &str) -> String {
+    let mut command = String::new();
+    let mut has_path = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            command.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => command.push('%'),
+            Some('f' | 'F' | 'u' | 'U') if !has_path => {
+                command.push_str("{}");
+                has_path = true;
+            },
+            _ => (),
+        }
+    }
+    command.trim().to_string()
+}
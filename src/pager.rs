@@ -0,0 +1,360 @@
+// mini pager, like `less`: scrolls through a file of any size by seeking around in it,
+// so only what's on screen is ever read. text is shown line by line, anything else as a hex dump
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+// a line this long without a newline gets split, so one giant line can't be read all at once
+const MAX_LINE_BYTES : u64 = 4096;
+// how far back to look for where a long line starts, so it's split in the same places going up as going down.
+// past this, splits just line up with the file offset instead
+const MAX_LINE_START_SEARCH_BYTES : u64 = 1024 * 1024;
+const HEX_BYTES_PER_LINE : u64 = 16;
+// how much to read while searching, at a time and per keypress. n keeps going from where it stopped
+const SEARCH_CHUNK_BYTES : u64 = 1024 * 1024;
+const MAX_SEARCH_BYTES_PER_KEYPRESS : u64 = 256 * 1024 * 1024;
+
+pub struct Pager {
+    pub path: PathBuf,
+    file: File,
+    pub len: u64,
+    pub hex: bool,
+    // byte offset of the first line on screen
+    pub top: u64,
+    rows: usize,
+    // what's on screen, reread after every move
+    pub lines: Vec<String>,
+    // the last search, for n and N
+    pub query: String,
+}
+
+impl Pager {
+    pub fn open(path: &Path, rows: usize) -> io::Result<Pager> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut pager = Pager {
+            path: path.to_path_buf(),
+            file,
+            len,
+            hex: false,
+            top: 0,
+            rows,
+            lines: Vec::new(),
+            query: String::new(),
+        };
+        pager.hex = !looks_like_text(&pager.read_at(0, 64 * 1024));
+        pager.reread_lines();
+        Ok(pager)
+    }
+
+    pub fn set_rows(&mut self, rows: usize) {
+        self.rows = rows;
+        self.reread_lines();
+    }
+
+    pub fn toggle_hex(&mut self) {
+        self.hex = !self.hex;
+        self.top = self.line_start_at(self.top);
+        self.reread_lines();
+    }
+
+    pub fn scroll_down(&mut self, num_lines: usize) {
+        for _ in 0..num_lines {
+            let next = self.next_line_start(self.top);
+            if next >= self.len { break; }
+            self.top = next;
+        }
+        self.reread_lines();
+    }
+
+    pub fn scroll_up(&mut self, num_lines: usize) {
+        for _ in 0..num_lines {
+            if self.top == 0 { break; }
+            self.top = self.line_start_at(self.top - 1);
+        }
+        self.reread_lines();
+    }
+
+    pub fn goto_start(&mut self) {
+        self.top = 0;
+        self.reread_lines();
+    }
+
+    // like less, the last line ends up at the bottom of the screen
+    pub fn goto_end(&mut self) {
+        self.top = self.line_start_at(self.len.saturating_sub(1));
+        self.scroll_up(self.rows.saturating_sub(1));
+    }
+
+    // how far down the screen is, eg. for "42%"
+    pub fn percent(&self) -> u64 {
+        match self.len {
+            0 => 100,
+            len => self.top * 100 / len,
+        }
+    }
+
+    // moves the line with the next match to the top of the screen.
+    // skip_top_line is for n, so it finds the match after the one already at the top.
+    // returns a message for the status line
+    pub fn search(&mut self, forward: bool, skip_top_line: bool) -> String {
+        if self.query.is_empty() {
+            return "nothing to search for yet, press / first".to_string();
+        }
+        let needle = self.query.clone().into_bytes();
+        let result = match forward {
+            true => {
+                let from = match skip_top_line {
+                    true => self.next_line_start(self.top),
+                    false => self.top,
+                };
+                self.find_forward(&needle, from)
+            },
+            false => self.find_backward(&needle, self.top),
+        };
+        let message = match result {
+            Search::Found(offset) => {
+                self.top = self.line_start_at(offset);
+                String::new()
+            },
+            Search::NotFound => format!("no more matches for \"{}\"", self.query),
+            // a multi-GB file would take a while, so stop and show where we got to
+            Search::StoppedAt(offset) => {
+                self.top = self.line_start_at(offset.min(self.len.saturating_sub(1)));
+                format!("no match in the next {} MB, press {} to keep looking",
+                        MAX_SEARCH_BYTES_PER_KEYPRESS / 1024 / 1024,
+                        if forward { "n" } else { "N" })
+            },
+        };
+        self.reread_lines();
+        message
+    }
+
+    fn find_forward(&self, needle: &[u8], from: u64) -> Search {
+        let mut offset = from;
+        while offset < self.len {
+            if offset - from >= MAX_SEARCH_BYTES_PER_KEYPRESS {
+                return Search::StoppedAt(offset);
+            }
+            // overlap chunks a bit, so a match across the boundary isn't missed
+            let chunk = self.read_at(offset, (SEARCH_CHUNK_BYTES + needle.len() as u64) as usize);
+            if let Some(index) = find_bytes(&chunk, needle) {
+                return Search::Found(offset + index as u64);
+            }
+            if chunk.len() < needle.len() { break; }
+            offset += SEARCH_CHUNK_BYTES;
+        }
+        Search::NotFound
+    }
+
+    // finds the last match that starts before `before`
+    fn find_backward(&self, needle: &[u8], before: u64) -> Search {
+        let mut end = before.saturating_add(needle.len() as u64 - 1).min(self.len);
+        while end > 0 {
+            if before.saturating_sub(end) >= MAX_SEARCH_BYTES_PER_KEYPRESS {
+                return Search::StoppedAt(end);
+            }
+            let start = end.saturating_sub(SEARCH_CHUNK_BYTES + needle.len() as u64);
+            let chunk = self.read_at(start, (end - start) as usize);
+            if let Some(index) = rfind_bytes(&chunk, needle) {
+                return Search::Found(start + index as u64);
+            }
+            if start == 0 { break; }
+            end = start + needle.len() as u64;
+        }
+        Search::NotFound
+    }
+
+    fn reread_lines(&mut self) {
+        let mut lines = Vec::new();
+        let mut offset = self.top;
+        while lines.len() < self.rows && offset < self.len {
+            let next = self.next_line_start(offset);
+            let bytes = self.read_at(offset, (next - offset) as usize);
+            lines.push(match self.hex {
+                true => format_hex_line(offset, &bytes),
+                false => format_text_line(&bytes),
+            });
+            offset = next;
+        }
+        self.lines = lines;
+    }
+
+    fn next_line_start(&self, offset: u64) -> u64 {
+        if self.hex {
+            return (offset + HEX_BYTES_PER_LINE).min(self.len);
+        }
+        let bytes = self.read_at(offset, MAX_LINE_BYTES as usize);
+        match bytes.iter().position(|&byte| byte == b'\n') {
+            Some(index) => offset + index as u64 + 1,
+            None => offset + bytes.len() as u64,
+        }
+    }
+
+    // the start of the line that the byte at offset is on.
+    // a long line is split every MAX_LINE_BYTES from where it starts, like next_line_start does
+    fn line_start_at(&self, offset: u64) -> u64 {
+        if self.hex {
+            return offset - offset % HEX_BYTES_PER_LINE;
+        }
+        let start = self.unsplit_line_start_at(offset);
+        start + (offset - start) / MAX_LINE_BYTES * MAX_LINE_BYTES
+    }
+
+    // the byte after the last newline before offset
+    fn unsplit_line_start_at(&self, offset: u64) -> u64 {
+        let mut end = offset;
+        while end > 0 {
+            // pretend a really long line starts at 0, which still splits it the same way every time
+            if offset - end >= MAX_LINE_START_SEARCH_BYTES { return 0; }
+            let start = end.saturating_sub(MAX_LINE_BYTES);
+            let bytes = self.read_at(start, (end - start) as usize);
+            if let Some(index) = bytes.iter().rposition(|&byte| byte == b'\n') {
+                return start + index as u64 + 1;
+            }
+            // eg. a read error, which would otherwise keep trying the same spot
+            if bytes.is_empty() { return start; }
+            end = start;
+        }
+        0
+    }
+
+    // reads what it can. errors just mean less to show
+    fn read_at(&self, offset: u64, len: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut file = &self.file;
+        if file.seek(SeekFrom::Start(offset)).is_ok() {
+            let _ = file.take(len as u64).read_to_end(&mut bytes);
+        }
+        bytes
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Search {
+    Found(u64),
+    NotFound,
+    StoppedAt(u64),
+}
+
+// skips ahead to the needle's first byte before comparing the rest,
+// which is much quicker than comparing at every position
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let mut start = 0;
+    while let Some(index) = haystack[start..].iter().position(|&byte| byte == needle[0]) {
+        let candidate = start + index;
+        if haystack[candidate..].starts_with(needle) {
+            return Some(candidate);
+        }
+        start = candidate + 1;
+    }
+    None
+}
+
+fn rfind_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let mut end = haystack.len();
+    while let Some(candidate) = haystack[..end].iter().rposition(|&byte| byte == needle[0]) {
+        if haystack[candidate..].starts_with(needle) {
+            return Some(candidate);
+        }
+        end = candidate;
+    }
+    None
+}
+
+// utf-8 without NULs. the sample may end halfway through a char
+fn looks_like_text(sample: &[u8]) -> bool {
+    let is_utf8 = match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(err) => err.error_len().is_none(),
+    };
+    is_utf8 && !sample.contains(&0)
+}
+
+fn format_text_line(bytes: &[u8]) -> String {
+    // tabs and escape codes would throw off the columns, or draw who knows what
+    String::from_utf8_lossy(bytes)
+        .replace('\t', "    ")
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect()
+}
+
+// eg. 00000010  48 65 6c 6c 6f 0a 00 00  00 00 00 00 00 00 00 00  |Hello...........|
+fn format_hex_line(offset: u64, bytes: &[u8]) -> String {
+    let mut hex = String::new();
+    for i in 0..HEX_BYTES_PER_LINE as usize {
+        match bytes.get(i) {
+            Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+            None => hex.push_str("   "),
+        }
+        if i == 7 { hex.push(' '); }
+    }
+    let ascii = bytes
+        .iter()
+        .map(|&byte| match byte {
+            0x20..=0x7e => byte as char,
+            _ => '.',
+        })
+        .collect::<String>();
+    format!("{:08x}  {} |{}|", offset, hex, ascii)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a pager on a fresh file with these contents
+    fn pager_with(test_name: &str, contents: &[u8]) -> (Pager, PathBuf) {
+        let path = std::env::temp_dir().join(format!("fmin_test_{}_{}", test_name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        (Pager::open(&path, 10).unwrap(), path)
+    }
+
+    #[test]
+    fn finds_matches_across_chunk_boundaries() {
+        let chunk = SEARCH_CHUNK_BYTES as usize;
+        let mut contents = vec![b'a'; 3 * chunk];
+        // straddles the first forward chunk's end
+        let forward_match = chunk - 3;
+        contents[forward_match..forward_match + 6].copy_from_slice(b"needle");
+        // straddles where the first backward chunk starts, reading back from the end
+        let backward_match = contents.len() - chunk - 6 - 3;
+        contents[backward_match..backward_match + 6].copy_from_slice(b"needle");
+        let (pager, path) = pager_with("pager_chunks", &contents);
+
+        assert_eq!(pager.find_forward(b"needle", 0), Search::Found(forward_match as u64));
+        assert_eq!(pager.find_forward(b"needle", forward_match as u64 + 1), Search::Found(backward_match as u64));
+        assert_eq!(pager.find_forward(b"needle", backward_match as u64 + 1), Search::NotFound);
+        assert_eq!(pager.find_backward(b"needle", pager.len), Search::Found(backward_match as u64));
+        // only matches that start before the given offset
+        assert_eq!(pager.find_backward(b"needle", backward_match as u64), Search::Found(forward_match as u64));
+        assert_eq!(pager.find_backward(b"needle", forward_match as u64), Search::NotFound);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn long_lines_split_the_same_way_up_and_down() {
+        let mut contents = b"ab\n".to_vec();
+        contents.extend(vec![b'x'; 3 * MAX_LINE_BYTES as usize + 100]);
+        contents.extend(b"\nlast\n");
+        let (pager, path) = pager_with("pager_long_lines", &contents);
+
+        assert_eq!(pager.line_start_at(0), 0);
+        assert_eq!(pager.line_start_at(2), 0);
+        assert_eq!(pager.line_start_at(3), 3);
+        // every split going down is a line start going up
+        let mut offset = 3;
+        while offset < pager.len {
+            let next = pager.next_line_start(offset);
+            assert_eq!(pager.line_start_at(next - 1), offset);
+            if next < pager.len {
+                assert_eq!(pager.line_start_at(next), next);
+            }
+            offset = next;
+        }
+        assert_eq!(pager.line_start_at(pager.len - 1), pager.len - 5);
+        let _ = std::fs::remove_file(&path);
+    }
+}
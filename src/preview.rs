@@ -0,0 +1,122 @@
+// mini preview pane: reads a glimpse of the entry under the cursor on another thread,
+// so holding j through a big dir never waits on the disk
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+
+use crate::{opener, read_directory_contents_into_sorted, FileDate, FileSize, Message, SortBy};
+
+// no screen is taller than this, and it keeps the cache small
+const MAX_LINES : usize = 200;
+// enough for MAX_LINES of any sane text file, without reading a whole log file
+const MAX_TEXT_BYTES : u64 = 64 * 1024;
+
+pub enum Preview {
+    // a dir's entries, sorted like the dir would be
+    Listing(Vec<String>),
+    // the first lines of a text file
+    Text(Vec<String>),
+    // everything else, described by its metadata
+    Summary(Vec<String>),
+}
+
+impl Preview {
+    pub fn lines(&self) -> &[String] {
+        match self {
+            Preview::Listing(lines) | Preview::Text(lines) | Preview::Summary(lines) => lines,
+        }
+    }
+}
+
+// the other end of the thread that reads previews, which sends them back as Message::PreviewLoaded
+pub struct Previewer {
+    requests: Sender<(PathBuf, SortBy)>,
+}
+
+impl Previewer {
+    pub fn start(messages: Sender<Message>) -> Previewer {
+        let (request_sender, request_receiver) = mpsc::channel::<(PathBuf, SortBy)>();
+        std::thread::spawn(move || {
+            while let Ok(mut request) = request_receiver.recv() {
+                // only the newest request matters, eg. where the cursor stopped after holding j
+                while let Ok(newer_request) = request_receiver.try_recv() {
+                    request = newer_request;
+                }
+                let (path, sort) = request;
+                let preview = read_preview(&path, sort);
+                if messages.send(Message::PreviewLoaded(path, preview)).is_err() { break; }
+            }
+        });
+        Previewer { requests: request_sender }
+    }
+
+    // sort is for dirs, so the preview matches what you'd see after going in
+    pub fn request(&self, path: &Path, sort: SortBy) {
+        // the thread only stops if it panicked, and then previews just stay empty
+        let _ = self.requests.send((path.to_path_buf(), sort));
+    }
+}
+
+fn read_preview(path: &Path, sort: SortBy) -> Preview {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) => return Preview::Summary(vec![format!("can't read: {}", err)]),
+    };
+    if metadata.is_dir() {
+        let entries = read_directory_contents_into_sorted(path, sort);
+        let lines = match entries.is_empty() {
+            true => vec!["(empty)".to_string()],
+            false => entries
+                .iter()
+                .take(MAX_LINES)
+                .map(|entry| entry.name.to_string())
+                .collect(),
+        };
+        return Preview::Listing(lines);
+    }
+    if let Some(lines) = read_text_lines(path) {
+        return Preview::Text(lines);
+    }
+    Preview::Summary(summarize(path, &metadata))
+}
+
+// None if it doesn't look like text
+fn read_text_lines(path: &Path) -> Option<Vec<String>> {
+    let mut head = Vec::new();
+    File::open(path).ok()?.take(MAX_TEXT_BYTES).read_to_end(&mut head).ok()?;
+    if head.contains(&0) { return None; }
+    // the last char may be cut off halfway
+    let text = match std::str::from_utf8(&head) {
+        Ok(text) => text,
+        Err(err) if err.error_len().is_none() => std::str::from_utf8(&head[..err.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    let lines = text
+        .lines()
+        .take(MAX_LINES)
+        // tabs and escape codes would throw off the columns, or draw who knows what
+        .map(|line| line.replace('\t', "    ").chars().filter(|c| !c.is_control()).collect())
+        .collect();
+    Some(lines)
+}
+
+fn summarize(path: &Path, metadata: &std::fs::Metadata) -> Vec<String> {
+    let mut lines = vec![
+        format!("type: {}", opener::detect_mime_types(path)[0]),
+        format!("size: {} ({} bytes)", FileSize(metadata.len()).to_string().trim(), metadata.len()),
+    ];
+    if let Ok(modified) = metadata.modified() {
+        lines.push(format!("modified: {}", FileDate(modified.into()).to_string().trim()));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        lines.push(format!("permissions: {:o}", metadata.permissions().mode() & 0o7777));
+    }
+    if let Ok(target) = std::fs::read_link(path) {
+        lines.push(format!("links to: {}", target.display()));
+    }
+    lines
+}
@@ -0,0 +1,258 @@
+// mini freedesktop.org trash, compatible with file managers like nautilus, dolphin, and trash-cli
+// https://specifications.freedesktop.org/trash-spec/trashspec-latest.html
+//
+// only uses the home trash, even for files on other drives,
+// which means trashing from a USB drive copies everything over to the home drive
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use crate::file_ops::{self, Progress};
+
+// where a trashed entry went, and where it came from
+pub struct TrashedEntry {
+    pub original_path: PathBuf,
+    pub trashed_path: PathBuf,
+}
+
+// $XDG_DATA_HOME/Trash, falling back to ~/.local/share/Trash like the spec says
+pub fn trash_dir() -> Option<PathBuf> {
+    match (std::env::var("XDG_DATA_HOME"), std::env::var("HOME")) {
+        (Ok(data_home), _) if !data_home.is_empty() => Some(PathBuf::from(data_home).join("Trash")),
+        (_, Ok(home)) => Some(PathBuf::from(home).join(".local/share/Trash")),
+        _ => None,
+    }
+}
+
+// where trashed entries themselves live, eg. ~/.local/share/Trash/files
+pub fn trash_files_dir() -> Option<PathBuf> {
+    trash_dir().map(|dir| dir.join("files"))
+}
+
+pub fn move_to_trash(path: &Path, progress: &Progress) -> io::Result<TrashedEntry> {
+    let trash_dir = trash_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no trash dir, set $XDG_DATA_HOME or $HOME"))?;
+    move_to_trash_in(&trash_dir, path, progress)
+}
+
+fn move_to_trash_in(trash_dir: &Path, path: &Path, progress: &Progress) -> io::Result<TrashedEntry> {
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    std::fs::create_dir_all(&files_dir)?;
+    std::fs::create_dir_all(&info_dir)?;
+
+    let original_path = absolute(path)?;
+    let filename = original_path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "can't trash a root dir"))?;
+
+    // claim a name by creating its .trashinfo file first, like the spec asks,
+    // so another program trashing a file with the same name at the same time can't take it
+    let mut n = 0;
+    let (trashed_path, info_path, mut info_file) = loop {
+        let trashed_path = file_ops::numbered_filename(&files_dir.join(filename), n);
+        let info_path = info_path_for(&info_dir, &trashed_path);
+        n += 1;
+        if std::fs::symlink_metadata(&trashed_path).is_ok() { continue; }
+        match std::fs::OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(file) => break (trashed_path, info_path, file),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    };
+
+    let result = write!(info_file,
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(&original_path),
+        Local::now().format("%Y-%m-%dT%H:%M:%S"),
+    ).and_then(|()| file_ops::move_path(&original_path, &trashed_path, progress));
+
+    match result {
+        Ok(()) => Ok(TrashedEntry { original_path, trashed_path }),
+        Err(err) => {
+            let _ = std::fs::remove_file(&info_path);
+            Err(err)
+        }
+    }
+}
+
+// puts an entry from Trash/files back where it was trashed from.
+// won't overwrite anything that's taken its place since then
+pub fn restore_from_trash(trashed_path: &Path, progress: &Progress) -> io::Result<PathBuf> {
+    // Trash/info is next to the Trash/files the entry is in
+    let info_dir = trashed_path
+        .parent()
+        .and_then(Path::parent)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not in a trash dir"))?
+        .join("info");
+    let info_path = info_path_for(&info_dir, trashed_path);
+    let info = std::fs::read_to_string(&info_path)?;
+    let original_path = info
+        .lines()
+        .find_map(|line| line.strip_prefix("Path="))
+        .map(percent_decode)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no Path= in .trashinfo"))?;
+
+    if std::fs::symlink_metadata(&original_path).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} already exists", original_path.display())));
+    }
+    if let Some(parent) = original_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    file_ops::move_path(trashed_path, &original_path, progress)?;
+    std::fs::remove_file(&info_path)?;
+    Ok(original_path)
+}
+
+// eg. Trash/files/notes.txt -> Trash/info/notes.txt.trashinfo
+fn info_path_for(info_dir: &Path, trashed_path: &Path) -> PathBuf {
+    let mut filename = trashed_path.file_name().unwrap_or_default().to_os_string();
+    filename.push(".trashinfo");
+    info_dir.join(filename)
+}
+
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    match path.is_absolute() {
+        true => Ok(path.to_path_buf()),
+        false => Ok(std::env::current_dir()?.join(path)),
+    }
+}
+
+// the spec wants paths escaped like in URLs, eg. "my notes.txt" -> "my%20notes.txt"
+pub fn percent_encode(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path_bytes(path) {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+pub fn percent_decode(encoded: &str) -> PathBuf {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            },
+        }
+    }
+    path_from_bytes(decoded)
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fresh, empty dir for one test. each test keeps its own trash in it, not the real one
+    fn test_dir(test_name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fmin_test_{}_{}", test_name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn paths_percent_encode_and_decode_back() {
+        assert_eq!(percent_encode(Path::new("/my notes/100% done.txt")), "/my%20notes/100%25%20done.txt");
+        assert_eq!(percent_encode(Path::new("/caf\u{e9},x")), "/caf%C3%A9%2Cx");
+        let mut paths = vec![PathBuf::from("/my notes/100% done.txt"), PathBuf::from("/caf\u{e9}/\u{1f600}"), PathBuf::from("/a%20b")];
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            paths.push(PathBuf::from(std::ffi::OsStr::from_bytes(b"/not utf-8 \xff\xfe")));
+        }
+        for path in paths {
+            assert_eq!(percent_decode(&percent_encode(&path)), path);
+        }
+        // written by something less careful, eg. by hand. kept as it is
+        assert_eq!(percent_decode("/100%/%zz%4"), PathBuf::from("/100%/%zz%4"));
+    }
+
+    #[test]
+    fn trashing_writes_trashinfo_and_restores() {
+        let dir = test_dir("trash_info");
+        let trash_dir = dir.join("Trash");
+        let original = dir.join("my notes.txt");
+        std::fs::write(&original, "notes").unwrap();
+
+        let trashed = move_to_trash_in(&trash_dir, &original, &Progress::default()).unwrap();
+        assert_eq!(trashed.original_path, original);
+        assert_eq!(trashed.trashed_path, trash_dir.join("files/my notes.txt"));
+        assert!(!original.exists());
+        let info = std::fs::read_to_string(trash_dir.join("info/my notes.txt.trashinfo")).unwrap();
+        let lines = info.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[0], "[Trash Info]");
+        assert_eq!(lines[1], format!("Path={}", percent_encode(&original)));
+        let date = lines[2].strip_prefix("DeletionDate=").unwrap();
+        assert!(chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S").is_ok(), "{}", date);
+        assert_eq!(lines.len(), 3);
+
+        assert_eq!(restore_from_trash(&trashed.trashed_path, &Progress::default()).unwrap(), original);
+        assert_eq!(std::fs::read_to_string(&original).unwrap(), "notes");
+        assert!(!trash_dir.join("info/my notes.txt.trashinfo").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn trashing_the_same_name_twice_keeps_both() {
+        let dir = test_dir("trash_collision");
+        let trash_dir = dir.join("Trash");
+        for sub in ["a", "b", "c"] {
+            std::fs::create_dir(dir.join(sub)).unwrap();
+            std::fs::write(dir.join(sub).join("notes.txt"), sub).unwrap();
+        }
+        // a name another program has claimed, but not moved its file into yet
+        std::fs::create_dir_all(trash_dir.join("info")).unwrap();
+        std::fs::write(trash_dir.join("info/notes_1.txt.trashinfo"), "").unwrap();
+
+        let trashed_paths = ["a", "b", "c"]
+            .iter()
+            .map(|sub| move_to_trash_in(&trash_dir, &dir.join(sub).join("notes.txt"), &Progress::default()).unwrap().trashed_path)
+            .collect::<Vec<PathBuf>>();
+        assert_eq!(trashed_paths, ["notes.txt", "notes_2.txt", "notes_3.txt"].map(|name| trash_dir.join("files").join(name)));
+        assert_eq!(std::fs::read_to_string(&trashed_paths[1]).unwrap(), "b");
+
+        // something took its place since, so restoring would overwrite it
+        std::fs::write(dir.join("b/notes.txt"), "new").unwrap();
+        assert_eq!(restore_from_trash(&trashed_paths[1], &Progress::default()).unwrap_err().kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(dir.join("b/notes.txt")).unwrap(), "new");
+        assert_eq!(restore_from_trash(&trashed_paths[2], &Progress::default()).unwrap(), dir.join("c/notes.txt"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,93 +1,337 @@
-// mini functional framework for application lifecycle and terminal display
-
-#![allow(unused_variables)]
-#![allow(unused_imports)]
-
-use std::path::{Path, PathBuf};
-use std::fs::DirEntry;
-use std::io::{Write};
-
-use crossterm::{
-    terminal,
-    queue,
-    execute,
-    cursor::MoveTo,
-    style::{Print,},
-    event::{
-        read as await_next_event, 
-        Event, 
-        KeyCode, 
-        KeyEvent, 
-        KeyModifiers,
-    },
-};
-
-pub struct Program<Init, View, Update> {
-    pub init: Init,
-    pub view: View,
-    pub update: Update,
-}
-
-pub enum UpdateResult {
-    Continue,
-    Finish,
-    Failed(String)
-}
-
-
-impl<Init, View, Update> Program<Init, View, Update> {
-    pub fn run<Model>(self) -> Result<Model, String>
-    where 
-        Init: FnOnce() -> Result<Model, String>,
-        View: Fn(&Model, &mut std::io::Stderr),
-        // update() mutates the model bc I think it's a bit easier and more performant
-        //   than creating a new Model in memory on each update
-        //   although maybe returning Model { newfield: _, ..oldmodel } would work fine
-        Update: Fn(&mut Model, Event) -> UpdateResult,
-    {
-        let Self {init, view, update} = self;
-        // write all TUI content to stderr, so on finish, stdout can pass information,
-        // like `cd (fmin)`
-        let mut stderr = std::io::stderr();
-
-        let mut model = init()?; // quit early here if init fails
-
-        // disables some behavior like line wrapping and catching Enter presses
-        // because i will handle those myself
-        // https://docs.rs/crossterm/latest/crossterm/terminal/index.html#raw-mode
-        terminal::enable_raw_mode(); 
-        queue!(stderr, 
-               terminal::EnterAlternateScreen,
-               terminal::DisableLineWrap,
-               crossterm::cursor::Hide,
-               crossterm::cursor::EnableBlinking, // for indicating focus of text inputs; cursor will be hidden anyways in other modes
-        );
-
-        view(&model, &mut stderr);
-        stderr.flush();
-
-        loop {
-            let event = await_next_event().unwrap();
-            match update(&mut model, event) {
-                UpdateResult::Continue => (),
-                UpdateResult::Finish => break,
-                UpdateResult::Failed(msg) => {
-                    return Err(msg);
-                    () // to satisfy compiler return type
-                }
-            };
-
-            view(&model, &mut stderr);
-            stderr.flush();
-        }
-
-        // cleanup and be a good citizen so the terminal behaves normally afterwards (eg. start catching ctrl+c again, and show cursor)
-        execute!(stderr, 
-                 terminal::EnableLineWrap,
-                 terminal::LeaveAlternateScreen,
-                 crossterm::cursor::Show,
-        );
-        terminal::disable_raw_mode(); 
-        Ok(model)
-    }
-}
+// mini functional framework for application lifecycle and terminal display
+
+#![allow(unused_variables)]
+#![allow(unused_imports)]
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::fs::DirEntry;
+use std::io::{self, Write};
+use std::process::{Command, ExitStatus};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crossterm::{
+    terminal,
+    queue,
+    execute,
+    cursor::MoveTo,
+    style::{Print,},
+    event::{
+        read as await_next_event, 
+        poll as wait_for_event,
+        Event, 
+        KeyCode, 
+        KeyEvent, 
+        KeyModifiers,
+    },
+};
+
+pub struct Program<Init, View, Update> {
+    pub init: Init,
+    pub view: View,
+    pub update: Update,
+}
+
+pub enum UpdateResult {
+    Continue,
+    // like Continue, but nothing changed, so skip redrawing. eg. a tick while nothing's happening
+    Unchanged,
+    Finish,
+    #[allow(dead_code)]
+    Failed(String),
+    // hand the terminal over to a child process like $EDITOR until it exits, then take it back.
+    // update() hears how it went from a ProgramEvent::Resumed
+    RunInTerminal(Command),
+    // step aside like any other program on ctrl+z, until the shell brings us back with `fg`
+    Suspend,
+}
+
+// everything update() hears about, in the order it happened.
+// Message is up to the app, eg. results from its own background threads
+pub enum ProgramEvent<Message> {
+    Terminal(Event),
+    // sent regularly, keys or not, so things like progress bars can move on their own
+    Tick,
+    // the child process from UpdateResult::RunInTerminal exited, or couldn't start
+    Resumed(io::Result<ExitStatus>),
+    // sent by the app through the Sender that init() gets, from any thread
+    Message(Message),
+}
+
+// what the main loop waits on: the terminal reader's events and the app's messages, in one channel
+enum Incoming<Message> {
+    Terminal(io::Result<Event>),
+    Message(Message),
+}
+
+const TICK_INTERVAL : Duration = Duration::from_millis(200);
+// how often the terminal reader checks whether to step aside for a child process
+const READER_PAUSE_CHECK_INTERVAL : Duration = Duration::from_millis(100);
+// crossterm can get stuck retrying reads from a terminal that's gone, eg. a closed window.
+// then the reader never answers, and there's nothing left to steal keypresses from anyway
+const MAX_READER_PAUSE_WAIT : Duration = Duration::from_secs(1);
+
+
+impl<Init, View, Update> Program<Init, View, Update> {
+    pub fn run<Model, Message>(self) -> Result<Model, String>
+    where 
+        // init() can hand out clones of the Sender, eg. to threads it starts
+        Init: FnOnce(Sender<Message>) -> Result<Model, String>,
+        View: Fn(&Model, &mut std::io::Stderr) -> io::Result<()>,
+        // update() mutates the model bc I think it's a bit easier and more performant
+        //   than creating a new Model in memory on each update
+        //   although maybe returning Model { newfield: _, ..oldmodel } would work fine
+        Update: Fn(&mut Model, ProgramEvent<Message>) -> UpdateResult,
+        // messages come from other threads, through a thread of our own
+        Message: Send + 'static,
+    {
+        let Self {init, view, update} = self;
+        // write all TUI content to stderr, so on finish, stdout can pass information,
+        // like `cd (fmin)`
+        let mut stderr = std::io::stderr();
+
+        let (incoming_sender, incoming) = mpsc::channel();
+        let (message_sender, messages) = mpsc::channel();
+        let mut model = init(message_sender)?; // quit early here if init fails
+        forward_messages(messages, incoming_sender.clone());
+
+        // eg. stderr isn't a terminal. nothing would work, so don't even start
+        if let Err(err) = take_terminal(&mut stderr) {
+            give_back_terminal(&mut stderr);
+            return Err(format!("can't set up the terminal: {}", err));
+        }
+        // incoming_sender itself lives until run() returns, so waiting on incoming never fails early
+        let reader = TerminalReader::start(incoming_sender.clone());
+
+        if let Err(err) = draw(&view, &model, &mut stderr) {
+            reader.pause();
+            give_back_terminal(&mut stderr);
+            return Err(format!("can't write to the terminal: {}", err));
+        }
+
+        // events from the program itself, like Resumed, go before anything from the terminal
+        let mut queued_events = VecDeque::new();
+        // arrived but not handled yet, eg. messages waiting behind a keypress
+        let mut pending = VecDeque::new();
+        let mut last_tick = Instant::now();
+        loop {
+            let event = match queued_events.pop_front() {
+                Some(event) => event,
+                None => match wait_for_next_event(&incoming, &mut pending, &mut last_tick) {
+                    Ok(event) => event,
+                    // eg. the terminal window was closed. without this, every read would fail instantly forever
+                    Err(err) => {
+                        reader.pause();
+                        give_back_terminal(&mut stderr);
+                        return Err(format!("can't read from the terminal: {}", err));
+                    },
+                },
+            };
+            match update(&mut model, event) {
+                UpdateResult::Continue => (),
+                UpdateResult::Unchanged => continue,
+                UpdateResult::Finish => break,
+                UpdateResult::Failed(msg) => {
+                    reader.pause();
+                    give_back_terminal(&mut stderr);
+                    return Err(msg);
+                },
+                UpdateResult::RunInTerminal(mut command) => {
+                    let status = match run_with_terminal_given_away(&mut stderr, &reader, &mut command) {
+                        Ok(status) => status,
+                        Err(err) => {
+                            give_back_terminal(&mut stderr);
+                            return Err(format!("can't set up the terminal again: {}", err));
+                        },
+                    };
+                    // the terminal may have been resized in the meantime
+                    queued_events.extend(current_size_event());
+                    queued_events.push_back(ProgramEvent::Resumed(status));
+                    continue;
+                },
+                UpdateResult::Suspend => {
+                    if let Err(err) = suspend(&mut stderr, &reader) {
+                        give_back_terminal(&mut stderr);
+                        return Err(format!("can't set up the terminal again: {}", err));
+                    }
+                    queued_events.extend(current_size_event());
+                },
+            };
+
+            if let Err(err) = draw(&view, &model, &mut stderr) {
+                reader.pause();
+                give_back_terminal(&mut stderr);
+                return Err(format!("can't write to the terminal: {}", err));
+            }
+        }
+
+        // so whatever's typed after fmin exits goes to the shell
+        reader.pause();
+        give_back_terminal(&mut stderr);
+        Ok(model)
+    }
+}
+
+// whichever comes first: a tick that's due, something from the terminal, or a message.
+// sleeps in between, instead of checking now and then.
+// keys go before messages, so a thread sending lots of them can't make typing lag
+fn wait_for_next_event<Message>(
+    incoming: &Receiver<Incoming<Message>>,
+    pending: &mut VecDeque<Incoming<Message>>,
+    last_tick: &mut Instant,
+) -> io::Result<ProgramEvent<Message>> {
+    loop {
+        let until_tick = TICK_INTERVAL.saturating_sub(last_tick.elapsed());
+        if until_tick.is_zero() {
+            *last_tick = Instant::now();
+            return Ok(ProgramEvent::Tick);
+        }
+        pending.extend(incoming.try_iter());
+        let next = pending.iter().position(|event| matches!(event, Incoming::Terminal(_))).unwrap_or(0);
+        match pending.remove(next) {
+            Some(Incoming::Terminal(event)) => return Ok(ProgramEvent::Terminal(event?)),
+            Some(Incoming::Message(message)) => return Ok(ProgramEvent::Message(message)),
+            None => (),
+        };
+        // a timeout means the tick is due. run() keeps a Sender, so it's never disconnected
+        if let Ok(event) = incoming.recv_timeout(until_tick) {
+            pending.push_back(event);
+        }
+    }
+}
+
+// the app sends plain Messages, which are passed on into the channel the main loop waits on.
+// stops once the app drops every Sender
+fn forward_messages<Message: Send + 'static>(messages: Receiver<Message>, incoming: Sender<Incoming<Message>>) {
+    std::thread::spawn(move || {
+        for message in messages {
+            if incoming.send(Incoming::Message(message)).is_err() { break; }
+        }
+    });
+}
+
+// reads the terminal on its own thread, and sends what it reads to the main loop.
+// while a child process has the terminal, it's paused, so it can't steal keypresses meant for the child
+struct TerminalReader {
+    paused: Arc<AtomicBool>,
+    // the reader says it's stopped reading, so pause() can wait for it
+    pause_acks: Receiver<()>,
+    thread: JoinHandle<()>,
+}
+
+impl TerminalReader {
+    fn start<Message: Send + 'static>(incoming: Sender<Incoming<Message>>) -> TerminalReader {
+        let paused = Arc::new(AtomicBool::new(false));
+        let (ack_sender, pause_acks) = mpsc::channel();
+        let thread_paused = paused.clone();
+        let thread = std::thread::spawn(move || loop {
+            if thread_paused.load(Ordering::SeqCst) {
+                if ack_sender.send(()).is_err() { return; }
+                while thread_paused.load(Ordering::SeqCst) {
+                    std::thread::park();
+                }
+                continue;
+            }
+            // waits a bit at a time, since a read can't be interrupted when it's time to pause
+            let event = match wait_for_event(READER_PAUSE_CHECK_INTERVAL) {
+                Ok(true) => await_next_event(),
+                Ok(false) => continue,
+                Err(err) => Err(err),
+            };
+            let failed = event.is_err();
+            // the main loop stops on an error, so there's no point reading any further
+            if incoming.send(Incoming::Terminal(event)).is_err() || failed { return; }
+        });
+        TerminalReader { paused, pause_acks, thread }
+    }
+
+    // returns once the reader has stopped reading. anything typed before then still goes to the main loop
+    fn pause(&self) {
+        // a late answer to an earlier pause that gave up waiting
+        while self.pause_acks.try_recv().is_ok() {}
+        self.paused.store(true, Ordering::SeqCst);
+        // a disconnect means the thread has stopped for good, which is just as paused
+        let _ = self.pause_acks.recv_timeout(MAX_READER_PAUSE_WAIT);
+    }
+
+    fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.thread.thread().unpark();
+    }
+}
+
+fn draw<Model>(view: &impl Fn(&Model, &mut io::Stderr) -> io::Result<()>, model: &Model, stderr: &mut io::Stderr) -> io::Result<()> {
+    view(model, stderr)?;
+    stderr.flush()
+}
+
+fn take_terminal(stderr: &mut io::Stderr) -> io::Result<()> {
+    // disables some behavior like line wrapping and catching Enter presses
+    // because i will handle those myself
+    // https://docs.rs/crossterm/latest/crossterm/terminal/index.html#raw-mode
+    terminal::enable_raw_mode()?;
+    execute!(stderr, 
+             terminal::EnterAlternateScreen,
+             terminal::DisableLineWrap,
+             // anything left over from before would stay on screen where view() doesn't draw
+             terminal::Clear(terminal::ClearType::All),
+             crossterm::cursor::Hide,
+             crossterm::cursor::EnableBlinking, // for indicating focus of text inputs; cursor will be hidden anyways in other modes
+    )
+}
+
+// on the way out, so there's nothing left to do about errors but log them.
+// tries everything even if something fails, to leave the terminal as usable as it can
+fn give_back_terminal(stderr: &mut io::Stderr) {
+    // cleanup and be a good citizen so the terminal behaves normally afterwards (eg. start catching ctrl+c again, and show cursor)
+    if let Err(err) = execute!(stderr, 
+             terminal::EnableLineWrap,
+             terminal::LeaveAlternateScreen,
+             crossterm::cursor::Show,
+    ) {
+        log::warn!("couldn't restore the screen: {}", err);
+    }
+    if let Err(err) = terminal::disable_raw_mode() {
+        log::warn!("couldn't leave raw mode: {}", err);
+    }
+}
+
+// the outer error is from taking the terminal back, the inner one from running the command
+fn run_with_terminal_given_away(stderr: &mut io::Stderr, reader: &TerminalReader, command: &mut Command) -> io::Result<io::Result<ExitStatus>> {
+    reader.pause();
+    give_back_terminal(stderr);
+    // stdout is usually piped somewhere, like `cd (fmin)`, but the child needs to draw on the terminal
+    #[cfg(unix)]
+    if let Ok(tty) = std::fs::OpenOptions::new().write(true).open("/dev/tty") {
+        command.stdout(tty);
+    }
+    let status = command.status();
+    take_terminal(stderr)?;
+    reader.resume();
+    Ok(status)
+}
+
+// raw mode means ctrl+z arrives as a keypress instead of stopping us, so stop ourselves the same way
+fn suspend(stderr: &mut io::Stderr, reader: &TerminalReader) -> io::Result<()> {
+    reader.pause();
+    give_back_terminal(stderr);
+    // returns once we're continued. shells without job control never stop us at all,
+    // and then it's like nothing happened
+    #[cfg(unix)]
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+    take_terminal(stderr)?;
+    reader.resume();
+    Ok(())
+}
+
+fn current_size_event<Message>() -> Option<ProgramEvent<Message>> {
+    terminal::size()
+        .ok()
+        .map(|(cols, rows)| ProgramEvent::Terminal(Event::Resize(cols, rows)))
+}
@@ -0,0 +1,148 @@
+// mini dir watcher, so the cwd stays up to date while other programs change it, like a build writing files.
+// uses inotify on linux, and elsewhere (or if inotify fails, eg. out of watches) checks the dir's mtime now and then
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::Message;
+
+// how often the thread checks whether it's been dropped, while nothing's changing
+const STOP_CHECK_INTERVAL : Duration = Duration::from_millis(250);
+// a burst of changes, like `make` writing 50 files, becomes one refresh once it's been quiet this long
+const SETTLE_TIME : Duration = Duration::from_millis(100);
+// but something writing nonstop still gets shown now and then
+const MAX_SETTLE_TIME : Duration = Duration::from_secs(1);
+// only adding, removing and renaming entries changes a dir's mtime, so edits inside files are missed this way
+const MTIME_POLL_INTERVAL : Duration = Duration::from_secs(1);
+
+// the thread watching a dir, which sends Message::CwdChanged.
+// dropping this stops the thread, eg. after going to another dir
+pub struct Watcher {
+    // tells its messages apart from ones an older watcher sent just before it was dropped
+    pub id: usize,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Watcher {
+    pub fn start(dir: &Path, messages: Sender<Message>) -> Watcher {
+        static NEXT_ID : AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let dir = dir.to_path_buf();
+        std::thread::spawn(move || {
+            let send_changed = |changed| messages.send(Message::CwdChanged { watcher_id: id, changed }).is_ok();
+            #[cfg(target_os = "linux")]
+            if watch_with_inotify(&dir, &thread_stopped, &send_changed) { return; }
+            poll_mtime(&dir, &thread_stopped, &send_changed);
+        });
+        Watcher { id, stopped }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+// what a burst of inotify events adds up to
+#[cfg(target_os = "linux")]
+#[derive(Default)]
+struct InotifyChanges {
+    paths: HashSet<PathBuf>,
+    // more happened than inotify could queue up, so anything may have changed
+    overflowed: bool,
+    // the dir itself was deleted or moved away, so inotify won't hear about it anymore
+    dir_gone: bool,
+}
+
+// returns false if inotify isn't working, or stopped working bc the dir itself is gone,
+// so mtime polling can take over, eg. to notice the dir being made again
+#[cfg(target_os = "linux")]
+fn watch_with_inotify(dir: &Path, stopped: &AtomicBool, send_changed: &dyn Fn(Option<Vec<PathBuf>>) -> bool) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(dir_cstring) = CString::new(dir.as_os_str().as_bytes()) else { return false; };
+    let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+    if fd < 0 { return false; }
+    // file contents count once they're written and closed, not on every write
+    let mask = libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO
+        | libc::IN_CLOSE_WRITE | libc::IN_ATTRIB | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+    if unsafe { libc::inotify_add_watch(fd, dir_cstring.as_ptr(), mask) } < 0 {
+        unsafe { libc::close(fd); }
+        return false;
+    }
+
+    let mut still_watching = true;
+    while !stopped.load(Ordering::Relaxed) {
+        let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+        let num_ready = unsafe { libc::poll(&mut pollfd, 1, STOP_CHECK_INTERVAL.as_millis() as libc::c_int) };
+        if num_ready <= 0 { continue; }
+        let mut changes = InotifyChanges::default();
+        let burst_started = Instant::now();
+        while drain_inotify_events(fd, dir, &mut changes) && burst_started.elapsed() < MAX_SETTLE_TIME {
+            std::thread::sleep(SETTLE_TIME);
+        }
+        let changed = match changes.overflowed || changes.dir_gone {
+            true => None,
+            false => Some(changes.paths.into_iter().collect()),
+        };
+        if !send_changed(changed) { break; }
+        if changes.dir_gone {
+            still_watching = false;
+            break;
+        }
+    }
+    unsafe { libc::close(fd); }
+    still_watching
+}
+
+// reads whatever's queued up into changes. returns whether there was anything
+#[cfg(target_os = "linux")]
+fn drain_inotify_events(fd: libc::c_int, dir: &Path, changes: &mut InotifyChanges) -> bool {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    const EVENT_SIZE : usize = std::mem::size_of::<libc::inotify_event>();
+    // enough for at least one event with the longest possible name
+    let mut buffer = [0u8; 4096];
+    let mut got_events = false;
+    loop {
+        let num_bytes = unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+        if num_bytes <= 0 { return got_events; }
+        got_events = true;
+        // each event is followed by its name, padded with NULs
+        let mut offset = 0;
+        while offset + EVENT_SIZE <= num_bytes as usize {
+            let event = unsafe { std::ptr::read_unaligned(buffer.as_ptr().add(offset) as *const libc::inotify_event) };
+            let name_start = offset + EVENT_SIZE;
+            offset = name_start + event.len as usize;
+            if event.mask & libc::IN_Q_OVERFLOW != 0 { changes.overflowed = true; }
+            if event.mask & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF | libc::IN_IGNORED) != 0 { changes.dir_gone = true; }
+            let name = buffer[name_start..offset.min(num_bytes as usize)].split(|&byte| byte == 0).next().unwrap_or_default();
+            if !name.is_empty() {
+                changes.paths.insert(dir.join(OsStr::from_bytes(name)));
+            }
+        }
+    }
+}
+
+fn poll_mtime(dir: &Path, stopped: &AtomicBool, send_changed: &dyn Fn(Option<Vec<PathBuf>>) -> bool) {
+    let modified = |dir: &Path| -> Option<SystemTime> { std::fs::metadata(dir).ok()?.modified().ok() };
+    let mut last_modified = modified(dir);
+    while !stopped.load(Ordering::Relaxed) {
+        std::thread::sleep(MTIME_POLL_INTERVAL);
+        let now_modified = modified(dir);
+        if now_modified != last_modified {
+            last_modified = now_modified;
+            // mtimes don't say what changed
+            if !send_changed(None) { return; }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+// mini background worker, so copying hundreds of GBs doesn't freeze the ui.
+// runs one file operation at a time on another thread, reporting progress through atomics
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Sender;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::file_ops::{self, Progress};
+use crate::journal::JournalOp;
+use crate::trash;
+use crate::Message;
+
+pub enum Job {
+    Copy { paths: Vec<PathBuf>, to_dir: PathBuf },
+    Move { paths: Vec<PathBuf>, to_dir: PathBuf },
+    Trash { paths: Vec<PathBuf> },
+    Delete { paths: Vec<PathBuf> },
+    // paths in the trash's files dir, put back where they were trashed from
+    Restore { paths: Vec<PathBuf> },
+}
+
+// what happened, once the job is done or cancelled
+pub struct JobResult {
+    // eg. "copied", for "copied 3 entries"
+    pub verb: &'static str,
+    pub num_succeeded: usize,
+    // what can be undone. permanent deletes leave nothing to undo
+    pub ops: Vec<JournalOp>,
+    pub errors: Vec<String>,
+    pub cancelled: bool,
+}
+
+pub struct RunningJob {
+    // eg. "copying 3 entries"
+    pub description: String,
+    pub progress: Arc<Progress>,
+    pub started: Instant,
+    handle: JoinHandle<JobResult>,
+}
+
+impl RunningJob {
+    // sends Message::JobFinished once it's done, cancelled or not
+    pub fn start(job: Job, messages: Sender<Message>) -> RunningJob {
+        let (verb, paths) = match &job {
+            Job::Copy { paths, .. } => ("copying", paths),
+            Job::Move { paths, .. } => ("moving", paths),
+            Job::Trash { paths } => ("trashing", paths),
+            Job::Delete { paths } => ("deleting", paths),
+            Job::Restore { paths } => ("restoring", paths),
+        };
+        let description = format!("{} {}", verb, crate::describe_count(paths.len(), "entry", "entries"));
+        let progress = Arc::new(Progress::default());
+        let thread_progress = progress.clone();
+        RunningJob {
+            description,
+            progress,
+            started: Instant::now(),
+            handle: std::thread::spawn(move || {
+                let result = run_job(job, &thread_progress);
+                // the app only stops listening when it quits, and then it doesn't need telling
+                let _ = messages.send(Message::JobFinished);
+                result
+            }),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.progress.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    // blocks until the job is done, so only call after Message::JobFinished, or after cancel()
+    pub fn wait(self) -> JobResult {
+        match self.handle.join() {
+            Ok(result) => result,
+            // a panic on the worker thread, eg. out of memory. better to report than to crash the ui
+            Err(_) => JobResult {
+                verb: "finished",
+                num_succeeded: 0,
+                ops: Vec::new(),
+                errors: vec!["file operation crashed".to_string()],
+                cancelled: false,
+            },
+        }
+    }
+
+    // eg. Some(90 seconds), from how fast it's gone so far.
+    // None until there's enough to go on
+    pub fn estimate_time_left(&self) -> Option<Duration> {
+        let total_bytes = self.progress.total_bytes.load(Ordering::Relaxed);
+        let bytes_done = self.progress.bytes_done.load(Ordering::Relaxed);
+        let total_files = self.progress.total_files.load(Ordering::Relaxed);
+        let files_done = self.progress.files_done.load(Ordering::Relaxed);
+        // bytes are a better measure, except for things like deleting, where each file takes about as long
+        let fraction_done = match (total_bytes, total_files) {
+            (0, 0) => return None,
+            (0, _) => files_done as f64 / total_files as f64,
+            _ => bytes_done as f64 / total_bytes as f64,
+        };
+        let elapsed = self.started.elapsed().as_secs_f64();
+        if fraction_done <= 0.0 || elapsed < 1.0 { return None; }
+        Some(Duration::from_secs_f64(elapsed * (1.0 - fraction_done).max(0.0) / fraction_done))
+    }
+}
+
+fn run_job(job: Job, progress: &Progress) -> JobResult {
+    let (verb, paths) = match &job {
+        Job::Copy { paths, .. } => ("copied", paths),
+        Job::Move { paths, .. } => ("moved", paths),
+        Job::Trash { paths } => ("trashed", paths),
+        Job::Delete { paths } => ("deleted", paths),
+        Job::Restore { paths } => ("restored", paths),
+    };
+
+    // count everything first, for the progress bar. sizes are kept per entry,
+    // since renames finish instantly and have to be counted in one go
+    let sizes = paths
+        .iter()
+        .map(|path| file_ops::measure(std::slice::from_ref(path)))
+        .collect::<Vec<(usize, u64)>>();
+    progress.total_files.store(sizes.iter().map(|(num_files, _)| num_files).sum(), Ordering::Relaxed);
+    progress.total_bytes.store(sizes.iter().map(|(_, num_bytes)| num_bytes).sum(), Ordering::Relaxed);
+
+    let mut result = JobResult { verb, num_succeeded: 0, ops: Vec::new(), errors: Vec::new(), cancelled: false };
+    let (mut files_so_far, mut bytes_so_far) = (0, 0);
+    for (path, (num_files, num_bytes)) in paths.iter().zip(sizes) {
+        if progress.cancelled.load(Ordering::Relaxed) {
+            result.cancelled = true;
+            break;
+        }
+        let op = match &job {
+            Job::Copy { to_dir, .. } => copy_into(path, to_dir, progress),
+            Job::Move { to_dir, .. } => move_into(path, to_dir, progress),
+            Job::Trash { .. } => trash::move_to_trash(path, progress)
+                .map(|trashed| Some(JournalOp::Trashed { original: trashed.original_path, trashed: trashed.trashed_path })),
+            Job::Delete { .. } => file_ops::remove_recursively(path, progress).map(|()| None),
+            Job::Restore { .. } => trash::restore_from_trash(path, progress)
+                .map(|original| Some(JournalOp::Restored { original, trashed: path.clone() })),
+        };
+        match op {
+            Ok(op) => {
+                result.num_succeeded += 1;
+                result.ops.extend(op);
+            },
+            Err(err) if progress.cancelled.load(Ordering::Relaxed) => result.cancelled = true,
+            Err(err) => result.errors.push(format!("{}: {}", path.file_name().unwrap_or(path.as_os_str()).to_string_lossy(), err)),
+        }
+        // whatever happened, this entry is done with
+        files_so_far += num_files;
+        bytes_so_far += num_bytes;
+        progress.files_done.store(files_so_far, Ordering::Relaxed);
+        progress.bytes_done.store(bytes_so_far, Ordering::Relaxed);
+    }
+    result
+}
+
+fn copy_into(path: &Path, to_dir: &Path, progress: &Progress) -> std::io::Result<Option<JournalOp>> {
+    let filename = path.file_name().unwrap_or_default();
+    let destination = file_ops::unique_destination(&to_dir.join(filename));
+    // a half-finished copy is just clutter, so it's removed again
+    file_ops::copy_recursively(path, &destination, progress)?;
+    Ok(Some(JournalOp::Created(destination)))
+}
+
+fn move_into(path: &Path, to_dir: &Path, progress: &Progress) -> std::io::Result<Option<JournalOp>> {
+    // moving into the same dir would just rename to notes_1.txt, so leave it be
+    if path.parent() == Some(to_dir) { return Ok(None); }
+    let filename = path.file_name().unwrap_or_default();
+    let destination = file_ops::unique_destination(&to_dir.join(filename));
+    file_ops::move_path(path, &destination, progress)?;
+    Ok(Some(JournalOp::Moved { from: path.to_path_buf(), to: destination }))
+}